@@ -0,0 +1,98 @@
+use byteorder::LittleEndian;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use hash40::label_map::LabelMap;
+use hash40::{hash40, Hash40, ReadHash40, WriteHash40};
+
+fn bench_new(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Hash40::new");
+    for len in [8, 32, 128, 255] {
+        let string = "a".repeat(len);
+        group.bench_with_input(BenchmarkId::from_parameter(len), &string, |b, string| {
+            b.iter(|| Hash40::new(black_box(string)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_new_bulk(c: &mut Criterion) {
+    let strings = (0..100_000)
+        .map(|index| format!("param_{}", index))
+        .collect::<Vec<_>>();
+
+    c.bench_function("Hash40::new x100000", |b| {
+        b.iter(|| {
+            for string in &strings {
+                black_box(Hash40::new(black_box(string)));
+            }
+        })
+    });
+}
+
+fn bench_to_label(c: &mut Criterion) {
+    let labels = (0..10_000)
+        .map(|index| format!("label_{}", index))
+        .collect::<LabelMap>();
+    let hit = hash40("label_5000");
+    let miss = hash40("missing");
+
+    Hash40::with_label_map(&labels, || {
+        c.bench_function("Hash40::to_label hit", |b| {
+            b.iter(|| black_box(hit).to_label())
+        });
+        c.bench_function("Hash40::to_label miss", |b| {
+            b.iter(|| black_box(miss).to_label())
+        });
+    });
+}
+
+fn bench_read_hash40(c: &mut Criterion) {
+    let mut buffer = Vec::new();
+    for index in 0..10_000 {
+        buffer
+            .write_hash40::<LittleEndian>(hash40(&index.to_string()))
+            .unwrap();
+    }
+
+    c.bench_function("read_hash40 x10000", |b| {
+        b.iter(|| {
+            let mut reader = black_box(&buffer[..]);
+            while !reader.is_empty() {
+                black_box(reader.read_hash40::<LittleEndian>().unwrap());
+            }
+        })
+    });
+}
+
+#[cfg(feature = "rayon")]
+fn bench_par_custom_labels(c: &mut Criterion) {
+    let text = (0..100_000)
+        .map(|index| {
+            let label = format!("label_{}", index);
+            format!("{},{}\n", hash40(&label).to_hex_string(), label)
+        })
+        .collect::<String>();
+
+    let mut group = c.benchmark_group("custom labels x100000");
+    group.bench_function("sequential", |b| {
+        b.iter(|| LabelMap::read_custom_labels_from(black_box(text.as_bytes())).unwrap())
+    });
+    group.bench_function("parallel", |b| {
+        b.iter(|| LabelMap::par_read_custom_labels_from_str(black_box(&text)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_new,
+    bench_new_bulk,
+    bench_to_label,
+    bench_read_hash40
+);
+#[cfg(feature = "rayon")]
+criterion_group!(rayon_benches, bench_par_custom_labels);
+
+#[cfg(not(feature = "rayon"))]
+criterion_main!(benches);
+#[cfg(feature = "rayon")]
+criterion_main!(benches, rayon_benches);
@@ -0,0 +1,30 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=HASH40_LABELS");
+    if env::var_os("CARGO_FEATURE_EMBED_LABELS").is_none() {
+        return;
+    }
+
+    // the labels embedded by the `embed-labels` feature can be replaced at build time. Build
+    // scripts run in the package directory, so a relative path is resolved against it rather
+    // than against the crate depending on hash40
+    let path = env::var_os("HASH40_LABELS")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("labels/embedded.txt"));
+    let path = fs::canonicalize(&path).unwrap_or_else(|err| {
+        panic!(
+            "failed to find the label file to embed at {}: {}",
+            path.display(),
+            err
+        )
+    });
+    println!("cargo:rerun-if-changed={}", path.display());
+    println!(
+        "cargo:rustc-env=HASH40_EMBEDDED_LABELS_PATH={}",
+        path.display()
+    );
+}
@@ -1,3 +1,7 @@
+//! The raw hash40 algorithm, operating on `u64` values instead of [`Hash40`](crate::Hash40).
+//! These functions never consult the label map, and can be used in const contexts
+
+// The table is a constant, so hashing never builds or initializes any state at runtime
 const CRC_TABLE: [u32; 256] = [
     0x00000000, 0x77073096, 0xee0e612c, 0x990951ba, 0x076dc419, 0x706af48f, 0xe963a535, 0x9e6495a3,
     0x0edb8832, 0x79dcb8a4, 0xe0d5e91e, 0x97d2d988, 0x09b64c2b, 0x7eb17cbd, 0xe7b82d07, 0x90bf1d91,
@@ -33,6 +37,12 @@ const CRC_TABLE: [u32; 256] = [
     0xb3667a2e, 0xc4614ab8, 0x5d681b02, 0x2a6f2b94, 0xb40bbe37, 0xc30c8ea1, 0x5a05df1b, 0x2d02ef8d,
 ];
 
+/// Advances a CRC32 state by one byte. The state starts at 0xffffffff, and the checksum is
+/// the bitwise negation of the final state
+pub(crate) const fn crc32_update(hash: u32, byte: u8) -> u32 {
+    (hash >> 8) ^ CRC_TABLE[((byte as u32 ^ hash) & 0xff) as usize]
+}
+
 const fn make_odd_matrix() -> [u32; 32] {
     let mut out = [0u32; 32];
     out[0] = 0xedb88320;
@@ -70,19 +80,84 @@ const fn matrix_square(matrix: &[u32; 32]) -> [u32; 32] {
 }
 
 // until I can use the `crc` crate as well as lowercase the string,
-// I'll manually implement the algorithm here
+// I'll manually implement the algorithm here.
+/// Hashes a string, returning the raw 40-bit value. ASCII letters are hashed as lowercase
 pub const fn hash40(string: &str) -> u64 {
+    hash40_bytes(string.as_bytes())
+}
+
+/// Hashes raw bytes, returning the raw 40-bit value. ASCII letters are hashed as lowercase.
+///
+/// The checksum is CRC-32/ISO-HDLC (the IEEE polynomial, reflected, with an initial value and
+/// final xor of 0xffffffff), which is what Smash Ultimate uses for hash40
+pub const fn hash40_bytes(bytes: &[u8]) -> u64 {
     let mut hash: u32 = 0xffffffff;
-    let bytes = string.as_bytes();
+    let mut index = 0;
+    while index < bytes.len() {
+        hash = crc32_update(hash, bytes[index].to_ascii_lowercase());
+        index += 1;
+    }
+    (!hash) as u64 | (bytes.len() as u8 as u64) << 32
+}
+
+#[cfg(feature = "crc32fast")]
+/// Hashes raw bytes like [`hash40_bytes`], using the hardware-accelerated CRC32 from the
+/// `crc32fast` crate when the CPU supports it. This is faster for long inputs, but can't be
+/// used in const contexts. `crc32fast` only implements CRC-32/ISO-HDLC, so there is no fast
+/// path for [`hash40_cksum_bytes`]
+pub fn hash40_bytes_fast(bytes: &[u8]) -> u64 {
+    // the bytes are lowercased through a buffer on the stack, to avoid allocating
+    let mut buffer = [0u8; 256];
+    let mut hasher = crc32fast::Hasher::new();
+    for chunk in bytes.chunks(buffer.len()) {
+        let lowercase = &mut buffer[..chunk.len()];
+        lowercase.copy_from_slice(chunk);
+        lowercase.make_ascii_lowercase();
+        hasher.update(lowercase);
+    }
+    hasher.finalize() as u64 | (bytes.len() as u8 as u64) << 32
+}
+
+const CKSUM_TABLE: [u32; 256] = make_cksum_table();
+
+const fn make_cksum_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut crc = (n as u32) << 24;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x80000000 != 0 {
+                (crc << 1) ^ 0x04c11db7
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[n] = crc;
+        n += 1;
+    }
+    table
+}
+
+/// Hashes raw bytes like [`hash40_bytes`], but with the CRC-32/CKSUM checksum (the same
+/// polynomial, not reflected, with an initial value of 0 and a final xor of 0xffffffff). The
+/// length of the input is not appended to the data, unlike the POSIX `cksum` tool
+pub const fn hash40_cksum_bytes(bytes: &[u8]) -> u64 {
+    let mut hash: u32 = 0;
     let mut index = 0;
     while index < bytes.len() {
         let byte = bytes[index].to_ascii_lowercase();
-        hash = (hash >> 8) ^ CRC_TABLE[((byte as u32 ^ hash) & 0xff) as usize];
+        hash = (hash << 8) ^ CKSUM_TABLE[(((hash >> 24) ^ byte as u32) & 0xff) as usize];
         index += 1;
     }
     (!hash) as u64 | (bytes.len() as u8 as u64) << 32
 }
 
+/// Combines two raw hashes into the hash of their concatenated strings, without rehashing.
+///
+/// The CRC of `second` can only be combined using its length byte, so the result matches a
+/// single-pass hash only when the second string is at most 255 bytes long
 pub const fn hash40_concat(first: u64, second: u64) -> u64 {
     let crc1 = (first & 0xffffffff) as u32;
     let crc2 = (second & 0xffffffff) as u32;
@@ -124,7 +199,8 @@ pub const fn hash40_concat(first: u64, second: u64) -> u64 {
 
     let crc = out ^ crc2;
 
-    ((len1 + len2) << 32) | crc as u64
+    // the length byte wraps, the same as it does when hashing in a single pass
+    (((len1 + len2) & 0xff) << 32) | crc as u64
 }
 
 #[test]
@@ -138,3 +214,78 @@ fn test_algorithm() {
         hash40("helloworld")
     );
 }
+
+#[test]
+fn test_crc_variant() {
+    // the standard check value of CRC-32/ISO-HDLC
+    assert_eq!(hash40("123456789"), 0x09cbf43926);
+    assert_eq!(hash40("fighter"), 0x077a08c3fc);
+    assert_eq!(hash40("param"), 0x05a4fa7c89);
+    assert_eq!(hash40("top"), 0x031ed91fca);
+    assert_eq!(hash40("vl"), 0x027cb8e1fc);
+}
+
+#[cfg(test)]
+/// A small xorshift generator, so that tests on random input are deterministic
+fn xorshift() -> impl FnMut() -> u64 {
+    let mut state = 0x2545f4914f6cdd1du64;
+    move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    }
+}
+
+#[test]
+fn test_concat_matches_single_pass() {
+    let mut next = xorshift();
+    let mut random_string = |max_len: u64| {
+        let len = next() % (max_len + 1);
+        (0..len)
+            .map(|_| (b' ' + (next() % 95) as u8) as char)
+            .collect::<alloc::string::String>()
+    };
+
+    for _ in 0..500 {
+        let a = random_string(255);
+        let b = random_string(255);
+        assert_eq!(
+            hash40_concat(hash40(&a), hash40(&b)),
+            hash40(&alloc::format!("{}{}", a, b)),
+            "{:?} + {:?}",
+            a,
+            b
+        );
+    }
+}
+
+#[test]
+fn test_cksum_variant() {
+    assert_eq!(hash40_cksum_bytes(b""), 0x00ffffffff);
+    assert_eq!(hash40_cksum_bytes(b"123456789"), 0x09765e7680);
+    assert_eq!(
+        hash40_cksum_bytes(b"FIGHTER"),
+        hash40_cksum_bytes(b"fighter")
+    );
+}
+
+#[test]
+#[cfg(feature = "crc32fast")]
+fn test_fast_matches_reference() {
+    let mut next = xorshift();
+
+    for _ in 0..200 {
+        let len = (next() % 2048) as usize;
+        let bytes = (0..len)
+            .map(|_| next() as u8)
+            .collect::<alloc::vec::Vec<_>>();
+        assert_eq!(
+            hash40_bytes_fast(&bytes),
+            hash40_bytes(&bytes),
+            "{:?}",
+            bytes
+        );
+    }
+    assert_eq!(hash40_bytes_fast(b"FIGHTER"), 0x077a08c3fc);
+}
@@ -0,0 +1,61 @@
+//! binrw helpers for hashes stored in exactly 5 bytes, rather than a full u64. Use with
+//! `#[br(parse_with = hash40::binrw_5byte::read)]` and
+//! `#[bw(write_with = hash40::binrw_5byte::write)]`
+
+use crate::Hash40;
+use binrw::io::{Read, Seek, Write};
+use binrw::{BinResult, Endian};
+
+/// Reads a 5-byte hash, in the endianness of the surrounding type
+pub fn read<R: Read + Seek>(reader: &mut R, endian: Endian, _: ()) -> BinResult<Hash40> {
+    let mut bytes = [0u8; 5];
+    reader.read_exact(&mut bytes)?;
+    Ok(match endian {
+        Endian::Little => Hash40::from_le_bytes(bytes),
+        Endian::Big => Hash40::from_be_bytes(bytes),
+    })
+}
+
+/// Writes a 5-byte hash, in the endianness of the surrounding type
+pub fn write<W: Write + Seek>(
+    hash: &Hash40,
+    writer: &mut W,
+    endian: Endian,
+    _: (),
+) -> BinResult<()> {
+    let bytes = match endian {
+        Endian::Little => hash.to_le_bytes(),
+        Endian::Big => hash.to_be_bytes(),
+    };
+    writer.write_all(&bytes)?;
+    Ok(())
+}
+
+#[test]
+fn test_binrw_5byte() {
+    use binrw::{binrw, io::Cursor, BinRead, BinWrite};
+
+    #[binrw]
+    #[brw(little)]
+    #[derive(Debug, PartialEq)]
+    struct Entry {
+        #[br(parse_with = read)]
+        #[bw(write_with = write)]
+        hash: Hash40,
+        value: u8,
+    }
+
+    let bytes = [0x43, 0xbe, 0xb7, 0xe8, 0x01, 0xff];
+    let entry = Entry::read(&mut Cursor::new(&bytes)).unwrap();
+    assert_eq!(
+        entry,
+        Entry {
+            hash: crate::hash40("a"),
+            value: 0xff
+        }
+    );
+
+    let mut written = Cursor::new(Vec::new());
+    entry.write(&mut written).unwrap();
+    assert_eq!(written.into_inner(), bytes);
+}
@@ -0,0 +1,52 @@
+//! binrw helpers which validate parsed hashes against the static label map (or the current
+//! thread's override, see [`Hash40::with_label_map`]). When the map is
+//! strict, any hash without a label is an error, so that unknown hashes are caught while
+//! parsing rather than when displayed. Use with
+//! `#[br(parse_with = hash40::binrw_checked::read)]` or
+//! `#[br(try_map = hash40::binrw_checked::try_map)]` on a u64
+
+use crate::errors::UnlabelledHashError;
+use crate::label_map::LabelMap;
+use crate::{with_labels, Hash40};
+use binrw::io::{Read, Seek};
+use binrw::{BinRead, BinResult, Endian};
+
+/// Reads a u64 hash, in the endianness of the surrounding type, and validates it with
+/// [`try_map`]
+pub fn read<R: Read + Seek>(reader: &mut R, endian: Endian, _: ()) -> BinResult<Hash40> {
+    let pos = reader.stream_position()?;
+    let value = u64::read_options(reader, endian, ())?;
+    try_map(value).map_err(|err| binrw::Error::Custom {
+        pos,
+        err: Box::new(err),
+    })
+}
+
+/// Masks the value to 40 bits, then returns an error if the static label map is strict and
+/// has no label for the hash
+pub fn try_map(value: u64) -> Result<Hash40, UnlabelledHashError> {
+    with_labels(|labels| check(labels, value))
+}
+
+fn check(labels: &LabelMap, value: u64) -> Result<Hash40, UnlabelledHashError> {
+    let hash = Hash40(value & 0xff_ffff_ffff);
+    if labels.strict && !labels.contains_hash(hash) {
+        Err(UnlabelledHashError(hash))
+    } else {
+        Ok(hash)
+    }
+}
+
+#[test]
+fn test_check() {
+    let mut labels = crate::labels! { "fighter" };
+    let fighter = crate::hash40("fighter");
+
+    assert_eq!(check(&labels, 0x1234), Ok(Hash40(0x1234)));
+    labels.set_strict(true);
+    assert_eq!(check(&labels, fighter.0 | 0xff << 40), Ok(fighter));
+    assert_eq!(
+        check(&labels, 0x1234),
+        Err(UnlabelledHashError(Hash40(0x1234)))
+    );
+}
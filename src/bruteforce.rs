@@ -0,0 +1,135 @@
+//! Helpers for recovering the string of an unknown hash by trying every combination of
+//! characters. Since the length of the string is part of the hash, only candidates of that
+//! exact length need to be checked.
+
+use crate::{algorithm, Hash40};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// Searches for a string made of bytes from `charset` which hashes to `target`, returning the
+/// first match found. Returns `None` if the target's length is greater than `max_len`, or no
+/// combination matches. Because hashing ignores ASCII case, the charset only needs one case of
+/// each letter.
+pub fn search(target: Hash40, charset: &[u8], max_len: usize) -> Option<String> {
+    let len = target.str_len() as usize;
+    if len > max_len {
+        return None;
+    }
+    search_from(target, charset, &[], &AtomicBool::new(false))
+}
+
+#[cfg(feature = "rayon")]
+/// The same as [`search`], but the candidates are split by their first character across
+/// rayon's thread pool. Once any thread finds a match, the others stop early. If several
+/// strings match, whichever is found first is returned.
+pub fn search_parallel(target: Hash40, charset: &[u8], max_len: usize) -> Option<String> {
+    let len = target.str_len() as usize;
+    if len > max_len {
+        return None;
+    }
+    if len == 0 {
+        return search_from(target, charset, &[], &AtomicBool::new(false));
+    }
+
+    let found = AtomicBool::new(false);
+    charset.par_iter().find_map_any(|first| {
+        let result = search_from(target, charset, &[*first], &found);
+        if result.is_some() {
+            found.store(true, Ordering::Relaxed);
+        }
+        result
+    })
+}
+
+/// Searches every candidate of the target's length which starts with `prefix`, until `stop`
+/// is set
+fn search_from(target: Hash40, charset: &[u8], prefix: &[u8], stop: &AtomicBool) -> Option<String> {
+    let len = target.str_len() as usize;
+    if len < prefix.len() || (len > prefix.len() && charset.is_empty()) {
+        return None;
+    }
+
+    // states[i] is the CRC state after the first i bytes of the candidate
+    let mut states = vec![0xffff_ffff_u32; len + 1];
+    for (i, byte) in prefix.iter().enumerate() {
+        states[i + 1] = algorithm::crc32_update(states[i], byte.to_ascii_lowercase());
+    }
+
+    let mut indices = vec![0usize; len - prefix.len()];
+    let mut changed = 0;
+    loop {
+        for i in changed..indices.len() {
+            let state = states[prefix.len() + i];
+            let byte = charset[indices[i]].to_ascii_lowercase();
+            states[prefix.len() + i + 1] = algorithm::crc32_update(state, byte);
+        }
+
+        if !states[len] == target.crc() {
+            let bytes = prefix
+                .iter()
+                .copied()
+                .chain(indices.iter().map(|&i| charset[i]))
+                .collect::<Vec<_>>();
+            if let Ok(found) = String::from_utf8(bytes) {
+                return Some(found);
+            }
+        }
+
+        // advance to the next candidate, like an odometer
+        let mut pos = indices.len();
+        loop {
+            if pos == 0 {
+                return None;
+            }
+            pos -= 1;
+            indices[pos] += 1;
+            if indices[pos] < charset.len() {
+                break;
+            }
+            indices[pos] = 0;
+        }
+        if pos + 1 < indices.len() && stop.load(Ordering::Relaxed) {
+            return None;
+        }
+        changed = pos;
+    }
+}
+
+#[test]
+fn test_search() {
+    let charset = b"abcdefghijklmnopqrstuvwxyz_";
+    assert_eq!(
+        search(crate::hash40("top"), charset, 4),
+        Some(String::from("top"))
+    );
+    assert_eq!(
+        search(crate::hash40("vl"), charset, 4),
+        Some(String::from("vl"))
+    );
+    assert_eq!(search(crate::hash40(""), charset, 4), Some(String::new()));
+    assert_eq!(search(crate::hash40("fighter"), charset, 4), None);
+    assert_eq!(search(crate::hash40("top"), b"xyz", 4), None);
+}
+
+#[test]
+#[cfg(feature = "rayon")]
+fn test_search_parallel() {
+    let charset = b"abcdefghijklmnopqrstuvwxyz_";
+    assert_eq!(
+        search_parallel(crate::hash40("top"), charset, 4),
+        Some(String::from("top"))
+    );
+    assert_eq!(
+        search_parallel(crate::hash40("cpu_"), charset, 4),
+        Some(String::from("cpu_"))
+    );
+    assert_eq!(
+        search_parallel(crate::hash40(""), charset, 4),
+        Some(String::new())
+    );
+    assert_eq!(search_parallel(crate::hash40("fighter"), charset, 4), None);
+}
@@ -0,0 +1,76 @@
+//! A wrapper around [`Hash40`] whose diff records both the old and the new value, for tools
+//! which report changes in a human-readable form
+
+use crate::Hash40;
+use diff::Diff;
+
+/// A [`Hash40`] whose [`Diff`] implementation produces a [`Hash40Change`] instead of only the
+/// new value. Applying a diff has the same effect as it does for [`Hash40`]
+#[derive(Debug, Default, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DiffableHash40(pub Hash40);
+
+/// A change between two hashes, produced by diffing two [`DiffableHash40`]s
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct Hash40Change {
+    /// The original hash
+    pub from: Hash40,
+    /// The hash it was changed to
+    pub to: Hash40,
+}
+
+impl From<Hash40> for DiffableHash40 {
+    fn from(hash: Hash40) -> Self {
+        Self(hash)
+    }
+}
+
+impl From<DiffableHash40> for Hash40 {
+    fn from(hash: DiffableHash40) -> Self {
+        hash.0
+    }
+}
+
+impl Diff for DiffableHash40 {
+    type Repr = Option<Hash40Change>;
+
+    fn diff(&self, other: &Self) -> Self::Repr {
+        if self == other {
+            None
+        } else {
+            Some(Hash40Change {
+                from: self.0,
+                to: other.0,
+            })
+        }
+    }
+
+    fn apply(&mut self, diff: &Self::Repr) {
+        if let Some(change) = diff {
+            self.0 = change.to;
+        }
+    }
+
+    fn identity() -> Self {
+        Default::default()
+    }
+}
+
+#[test]
+fn test_diff_change() {
+    use crate::hash40;
+
+    let mut fighter = DiffableHash40(hash40("fighter"));
+    let stage = DiffableHash40(hash40("stage"));
+    assert_eq!(fighter.diff(&fighter), None);
+
+    let change = fighter.diff(&stage);
+    assert_eq!(
+        change,
+        Some(Hash40Change {
+            from: hash40("fighter"),
+            to: hash40("stage"),
+        })
+    );
+    fighter.apply(&change);
+    assert_eq!(fighter, stage);
+}
@@ -1,37 +1,162 @@
-use std::fmt::{Display, Formatter};
-use std::num::ParseIntError;
+use crate::Hash40;
+use alloc::string::String;
+use core::fmt::{Display, Formatter};
+use core::num::ParseIntError;
+#[cfg(feature = "std")]
+use std::error::Error;
+#[cfg(feature = "std")]
+use std::io;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum ParseHashError {
     /// The error returned when the numeric hash string doesn't begin with "0x"
     MissingPrefix,
     /// The error returned when the hexadecimal part of the hash string cannot be parsed
-    ParseError(ParseIntError),
+    ParseError {
+        /// The whole hash string, including the prefix
+        text: String,
+        error: ParseIntError,
+    },
+    /// The error returned when the hexadecimal value doesn't fit in 40 bits
+    Overflow {
+        /// The whole hash string, including the prefix
+        text: String,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum FromLabelError {
     /// The error returned only when the static label map is bidirectional, and a label
     /// cannot be matched to a hash
     LabelNotFound(String),
     /// The error returned when the hexadecimal part of the hash string cannot be parsed
-    ParseError(ParseIntError),
+    ParseError {
+        /// The whole hash string, including the prefix
+        text: String,
+        error: ParseIntError,
+    },
+    /// The error returned when the hexadecimal value of the hash string doesn't fit in 40 bits
+    Overflow(String),
 }
 
-impl From<ParseIntError> for ParseHashError {
-    fn from(err: ParseIntError) -> Self {
-        Self::ParseError(err)
+/// The error returned when converting a u64 to a Hash40, if any bits above the lower 40 are set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hash40RangeError(pub u64);
+
+/// The error returned when a hash is required to have a label in the static label map, but
+/// doesn't
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnlabelledHashError(pub Hash40);
+
+impl Display for FromLabelError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::LabelNotFound(label) => write!(f, "label {:?} is not in the label map", label),
+            Self::ParseError { text, error } => {
+                write!(f, "failed to parse hex hash {:?}: {}", text, error)
+            }
+            Self::Overflow(text) => write!(f, "hex hash {:?} does not fit in 40 bits", text),
+        }
     }
 }
 
-impl From<ParseIntError> for FromLabelError {
-    fn from(err: ParseIntError) -> Self {
-        Self::ParseError(err)
+#[cfg(feature = "std")]
+impl Error for FromLabelError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::LabelNotFound(_) | Self::Overflow(_) => None,
+            Self::ParseError { error, .. } => Some(error),
+        }
     }
 }
 
-impl Display for FromLabelError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+#[cfg(feature = "std")]
+impl From<FromLabelError> for io::Error {
+    fn from(err: FromLabelError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+impl Display for ParseHashError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::MissingPrefix => write!(f, "hash string is missing the \"0x\" prefix"),
+            Self::ParseError { text, error } => {
+                write!(f, "failed to parse hex hash {:?}: {}", text, error)
+            }
+            Self::Overflow { text } => write!(f, "hex hash {:?} does not fit in 40 bits", text),
+        }
     }
 }
+
+#[cfg(feature = "std")]
+impl Error for ParseHashError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::MissingPrefix | Self::Overflow { .. } => None,
+            Self::ParseError { error, .. } => Some(error),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<ParseHashError> for io::Error {
+    fn from(err: ParseHashError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+impl Display for Hash40RangeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "value {:#x} does not fit in 40 bits", self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for Hash40RangeError {}
+
+impl Display for UnlabelledHashError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "hash {} has no label", self.0.to_hex_string())
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for UnlabelledHashError {}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_into_io_error() {
+    let err = io::Error::from(FromLabelError::LabelNotFound(String::from("stage")));
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(
+        err.into_inner().unwrap().downcast::<FromLabelError>().ok(),
+        Some(Box::new(FromLabelError::LabelNotFound(String::from(
+            "stage"
+        ))))
+    );
+
+    let err = io::Error::from(ParseHashError::MissingPrefix);
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_from_label_error_display() {
+    use alloc::string::ToString;
+
+    let error = u64::from_str_radix("ZZZ", 16).unwrap_err();
+    assert_eq!(
+        FromLabelError::ParseError {
+            text: String::from("0xZZZ"),
+            error,
+        }
+        .to_string(),
+        "failed to parse hex hash \"0xZZZ\": invalid digit found in string"
+    );
+    assert_eq!(
+        FromLabelError::LabelNotFound(String::from("stage")).to_string(),
+        "label \"stage\" is not in the label map"
+    );
+}
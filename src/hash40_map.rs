@@ -0,0 +1,137 @@
+use crate::Hash40;
+
+use std::collections::HashMap;
+use std::iter::FromIterator;
+use std::ops::{Deref, DerefMut};
+
+#[cfg(feature = "serde")]
+use serde::{de, ser::SerializeMap, Deserialize, Deserializer, Serialize, Serializer};
+#[cfg(feature = "serde")]
+use std::marker::PhantomData;
+
+/// A thin wrapper around a `HashMap` keyed by [`Hash40`]. When serialized, the keys are always
+/// written as strings through [`Hash40::to_label`], and read back through [`Hash40::from_label`],
+/// which lets formats requiring string keys (like JSON) store it. Keys which resolve to the
+/// same hash are rejected when deserializing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hash40Map<V>(pub HashMap<Hash40, V>);
+
+impl<V> Hash40Map<V> {
+    /// Creates an empty map
+    pub fn new() -> Self {
+        Self(HashMap::new())
+    }
+}
+
+impl<V> Default for Hash40Map<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> Deref for Hash40Map<V> {
+    type Target = HashMap<Hash40, V>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<V> DerefMut for Hash40Map<V> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<V> From<HashMap<Hash40, V>> for Hash40Map<V> {
+    fn from(map: HashMap<Hash40, V>) -> Self {
+        Self(map)
+    }
+}
+
+impl<V> FromIterator<(Hash40, V)> for Hash40Map<V> {
+    fn from_iter<I: IntoIterator<Item = (Hash40, V)>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl<V> IntoIterator for Hash40Map<V> {
+    type Item = (Hash40, V);
+    type IntoIter = std::collections::hash_map::IntoIter<Hash40, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<V: Serialize> Serialize for Hash40Map<V> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (hash, value) in &self.0 {
+            map.serialize_entry(&hash.to_label(), value)?;
+        }
+        map.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, V: Deserialize<'de>> Deserialize<'de> for Hash40Map<V> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(Hash40MapVisitor(PhantomData))
+    }
+}
+
+#[cfg(feature = "serde")]
+/// Used to implement serde's Deserialize trait
+struct Hash40MapVisitor<V>(PhantomData<V>);
+
+#[cfg(feature = "serde")]
+impl<'de, V: Deserialize<'de>> de::Visitor<'de> for Hash40MapVisitor<V> {
+    type Value = Hash40Map<V>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("A map with hash labels as keys")
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+        let mut map = HashMap::with_capacity(access.size_hint().unwrap_or(0));
+        while let Some((label, value)) = access.next_entry::<String, V>()? {
+            let hash = Hash40::from_label(&label).map_err(de::Error::custom)?;
+            if map.insert(hash, value).is_some() {
+                return Err(de::Error::custom(format!(
+                    "duplicate hash {} for key \"{}\"",
+                    hash.to_hex_string(),
+                    label
+                )));
+            }
+        }
+        Ok(Hash40Map(map))
+    }
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn test_hash40_map_serde() {
+    let map = vec![(Hash40(0x1234), 1), (crate::hash40("fighter"), 2)]
+        .into_iter()
+        .collect::<Hash40Map<_>>();
+    let json = serde_json::to_string(&map).unwrap();
+    assert!(json.contains(r#""0x0000001234":1"#));
+    assert!(json.contains(r#""0x077a08c3fc":2"#));
+    assert_eq!(serde_json::from_str::<Hash40Map<i32>>(&json).unwrap(), map);
+    assert_eq!(
+        serde_json::from_str::<Hash40Map<i32>>(r#"{"fighter":2}"#).unwrap()
+            [&crate::hash40("fighter")],
+        2
+    );
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn test_hash40_map_duplicate_keys() {
+    let error = serde_json::from_str::<Hash40Map<i32>>(r#"{"fighter":1,"0x077a08c3fc":2}"#)
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("duplicate hash 0x077a08c3fc"), "{}", error);
+}
@@ -1,90 +1,660 @@
-use crate::errors::ParseHashError;
-use crate::{hash40, Hash40};
-use bimap::BiHashMap;
+use crate::errors::{FromLabelError, ParseHashError};
+use crate::{hash40, write_labels, Hash40};
+use bimap::{BiHashMap, Overwritten};
+use diff::Diff;
+#[cfg(feature = "flate2")]
+use flate2::read::GzDecoder;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{
+    de,
+    ser::{SerializeMap, SerializeStruct},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
 
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::error::Error;
+use std::ffi::OsString;
+use std::fmt::{Display, Formatter};
 use std::fs::File;
-use std::io::{self, BufRead, BufReader};
-use std::path::Path;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::iter::FromIterator;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Default, Clone)]
 pub struct LabelMap {
     /// A bidirectional map to associate hashes and their labels
-    pub map: BiHashMap<Hash40, String>,
+    map: BiHashMap<Hash40, String>,
 
     /// Controls whether the default hash40 method is used instead of returning None
     /// when you try to find the hash of a label which is not present in the map.
     ///
     /// By default, set to false
     pub strict: bool,
+
+    /// Controls whether [`Self::hash_of`] ignores the case of labels. Set with
+    /// [`Self::set_case_insensitive`].
+    ///
+    /// By default, set to false
+    case_insensitive: bool,
+
+    /// An index of lowercased labels, maintained while `case_insensitive` is set. Labels which
+    /// only differ by case share a key, and the last hash in its list is found
+    lowercase_labels: HashMap<String, Vec<Hash40>>,
+
+    /// Controls whether an index of hashes by their CRC32 is maintained, so that
+    /// [`Self::find_by_crc`] doesn't need to search the whole map. Set with
+    /// [`Self::set_build_crc_index`].
+    ///
+    /// By default, set to false
+    build_crc_index: bool,
+
+    /// An index of hashes by their CRC32, maintained while `build_crc_index` is set
+    crc_index: HashMap<u32, Vec<Hash40>>,
+
+    /// Whether labels have ever been added to the map, so that a map which was loaded with
+    /// no labels can be distinguished from one which was never loaded
+    initialized: bool,
+}
+
+#[cfg(feature = "embed-labels")]
+/// The label file selected by the build script
+const EMBEDDED_LABELS: &str = include_str!(env!("HASH40_EMBEDDED_LABELS_PATH"));
+
+/// The environment variable holding the path of the label file loaded by
+/// [`LabelMapBuilder::from_env`]
+pub const LABEL_FILE_ENV: &str = "HASH40_LABEL_FILE";
+
+/// The environment variable setting the `strict` flag of the map loaded by
+/// [`LabelMapBuilder::from_env`]. The map is strict when this is `1` or `true`, ignoring case
+pub const STRICT_ENV: &str = "HASH40_STRICT";
+
+/// A builder which loads a [`LabelMap`] from files, and can install it as the static label map
+/// in a single step. Created with [`crate::Hash40::configure`] or [`LabelMapBuilder::default`]
+#[derive(Debug, Default, Clone)]
+pub struct LabelMapBuilder {
+    sources: Vec<LabelSource>,
+    strict: bool,
+}
+
+#[derive(Debug, Clone)]
+enum LabelSource {
+    Labels(PathBuf),
+    CustomLabels(PathBuf),
+}
+
+/// The layout of a custom label file, for [`LabelMap::read_custom_labels_with_options`]. The
+/// default options describe the `hash,label` layout read by [`LabelMap::read_custom_labels`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomLabelOptions {
+    /// The index of the column containing the hash, starting at 0
+    pub hash_column: usize,
+    /// The index of the column containing the label, starting at 0, which must differ from
+    /// `hash_column`. If this is the last column which is read, the label keeps the remainder
+    /// of the line, including any further delimiters
+    pub label_column: usize,
+    /// The character separating the columns
+    pub delimiter: char,
+}
+
+#[cfg(feature = "ndjson")]
+#[derive(Deserialize)]
+struct NdjsonLabel {
+    hash: String,
+    label: String,
+}
+
+/// An iterator over the hash-label pairs of a [`LabelMap`]
+pub struct Iter<'a> {
+    inner: bimap::hash::Iter<'a, Hash40, String>,
+}
+
+/// The difference between two [`LabelMap`]s, produced by [`Diff::diff`]. Each list is sorted
+/// by hash
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LabelMapDiff {
+    /// Pairs whose hash is only in the new map
+    pub added: Vec<(Hash40, String)>,
+    /// Hashes which are only in the old map
+    pub removed: Vec<Hash40>,
+    /// Pairs whose hash is in both maps, with the label from the new map
+    pub changed: Vec<(Hash40, String)>,
 }
 
 /// The type of error returned when reading from custom label files
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum CustomLabelError {
     Io(io::Error),
-    MisingColumn,
-    ParseHashError(ParseHashError),
+    /// The error returned when a line has no comma separating the hash and label.
+    /// The line number starts at 1
+    MissingColumn {
+        line: usize,
+    },
+    /// The error returned when [`CustomLabelOptions`] read the hash and label from the same
+    /// column, before any line is read
+    SameColumn {
+        column: usize,
+    },
+    /// The error returned when the hash on a line cannot be parsed.
+    /// The line number starts at 1
+    ParseHashError {
+        line: usize,
+        error: ParseHashError,
+    },
+    #[cfg(feature = "ndjson")]
+    /// The error returned when a line of an ndjson file isn't a valid object.
+    /// The line number starts at 1
+    Json {
+        line: usize,
+        error: serde_json::Error,
+    },
+}
+
+/// A summary of the labels inserted by [`LabelMap::add_labels_reporting`]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LoadReport {
+    /// Labels which were already in the map, including those repeated earlier in the same
+    /// input. A label is listed once for every repeat
+    pub duplicates: Vec<String>,
+    /// Distinct labels which hash to the same value, as `(hash, replaced, label)`. The `label`
+    /// was inserted in place of the `replaced` label
+    pub collisions: Vec<(Hash40, String, String)>,
+    /// Labels which were already in the map under a different hash, such as a custom label,
+    /// as `(old_hash, label)`. These pairs were replaced by the label under its computed hash
+    pub remapped: Vec<(Hash40, String)>,
+    /// The number of labels inserted, not counting duplicates
+    pub inserted: usize,
+}
+
+/// The error returned when inserting a label would overwrite an existing entry in the map
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LabelCollisionError {
+    /// The pair which could not be inserted
+    pub new: (Hash40, String),
+    /// The pair already in the map, which shares either its hash or label with the new pair
+    pub existing: (Hash40, String),
+}
+
+/// Builds a [`LabelMap`] inline. Bare labels are inserted under their computed hash, while
+/// `hash => label` entries insert a label for an explicit hash.
+///
+/// ```
+/// use hash40::{hash40, labels, Hash40};
+///
+/// let map = labels! { "fighter", "stage", 0x1234 => "custom" };
+/// assert_eq!(map.get_label(hash40("fighter")), Some("fighter"));
+/// assert_eq!(map.get_label(hash40("stage")), Some("stage"));
+/// assert_eq!(map.get_label(Hash40(0x1234)), Some("custom"));
+/// assert_eq!(map.hash_of("custom"), Some(Hash40(0x1234)));
+/// ```
+#[macro_export]
+macro_rules! labels {
+    (@insert $map:ident;) => {};
+    (@insert $map:ident; $hash:expr => $label:expr $(, $($rest:tt)*)?) => {
+        $map.insert($crate::Hash40($hash), ::std::string::String::from($label));
+        $($crate::labels!(@insert $map; $($rest)*);)?
+    };
+    (@insert $map:ident; $label:expr $(, $($rest:tt)*)?) => {
+        let label = ::std::string::String::from($label);
+        $map.insert($crate::Hash40::new(&label), label);
+        $($crate::labels!(@insert $map; $($rest)*);)?
+    };
+    () => {
+        $crate::label_map::LabelMap::default()
+    };
+    ($($entries:tt)+) => {{
+        let mut map = $crate::label_map::LabelMap::default();
+        $crate::labels!(@insert map; $($entries)+);
+        map
+    }};
 }
 
 impl LabelMap {
-    /// Convenience method to clear the labels within the map
+    /// Convenience method to clear the labels within the map. The map still counts as
+    /// initialized afterwards
     pub fn clear(&mut self) {
         self.map.clear();
+        self.lowercase_labels.clear();
+        self.crc_index.clear();
+    }
+
+    /// Returns the number of labels in the map
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns whether the map contains no labels
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Sets whether the map is strict. See [`Self::hash_of`] for how this affects lookups
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Sets whether the map is strict, returning the map for chaining. See [`Self::set_strict`]
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.set_strict(strict);
+        self
+    }
+
+    /// Returns whether the map is strict. See [`Self::hash_of`] for how this affects lookups
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Sets whether [`Self::hash_of`] ignores the case of labels. [`Self::label_of`] still
+    /// returns labels as they were inserted. Enabling it indexes every label already in the map,
+    /// and disabling it drops the index. When several labels only differ by case, which of
+    /// them is found is unspecified for labels indexed this way
+    pub fn set_case_insensitive(&mut self, case_insensitive: bool) {
+        self.case_insensitive = case_insensitive;
+        self.lowercase_labels.clear();
+        if case_insensitive {
+            for (hash, label) in self.map.iter() {
+                self.lowercase_labels
+                    .entry(label.to_lowercase())
+                    .or_default()
+                    .push(*hash);
+            }
+        }
+    }
+
+    /// Sets whether the map ignores the case of labels, returning the map for chaining. See
+    /// [`Self::set_case_insensitive`]
+    pub fn with_case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.set_case_insensitive(case_insensitive);
+        self
+    }
+
+    /// Returns whether [`Self::hash_of`] ignores the case of labels. See
+    /// [`Self::set_case_insensitive`]
+    pub fn is_case_insensitive(&self) -> bool {
+        self.case_insensitive
+    }
+
+    /// Sets whether an index of hashes by their CRC32 is maintained, so that
+    /// [`Self::find_by_crc`] doesn't need to search the whole map. Enabling it indexes every
+    /// pair already in the map, and disabling it drops the index
+    pub fn set_build_crc_index(&mut self, build_crc_index: bool) {
+        self.build_crc_index = build_crc_index;
+        self.crc_index.clear();
+        if build_crc_index {
+            for hash in self.map.left_values() {
+                self.crc_index.entry(hash.crc()).or_default().push(*hash);
+            }
+        }
+    }
+
+    /// Sets whether the CRC index is maintained, returning the map for chaining. See
+    /// [`Self::set_build_crc_index`]
+    pub fn with_build_crc_index(mut self, build_crc_index: bool) -> Self {
+        self.set_build_crc_index(build_crc_index);
+        self
+    }
+
+    /// Returns whether an index of hashes by their CRC32 is maintained. See
+    /// [`Self::set_build_crc_index`]
+    pub fn builds_crc_index(&self) -> bool {
+        self.build_crc_index
+    }
+
+    /// Returns whether any labels have been added to the map, even if the map is now empty
+    pub fn is_initialized(&self) -> bool {
+        self.initialized || !self.is_empty()
     }
 
     /// Inserts labels into the map, using the default hash40 method for the hash
     pub fn add_labels<I: IntoIterator<Item = String>>(&mut self, labels: I) {
+        self.initialized = true;
         for l in labels {
-            self.map.insert(Hash40::new(&l), l);
+            self.insert(Hash40::new(&l), l);
         }
     }
 
+    /// Inserts labels into the map like [`Self::add_labels`], but reports the labels which
+    /// were already in the map, and every pair which was replaced by an inserted label
+    pub fn add_labels_reporting<I: IntoIterator<Item = String>>(
+        &mut self,
+        labels: I,
+    ) -> LoadReport {
+        self.initialized = true;
+        let mut report = LoadReport::default();
+        for l in labels {
+            let hash = Hash40::new(&l);
+            match self.insert_overwritten(hash, l.clone()) {
+                Overwritten::Neither => {}
+                Overwritten::Pair(_, label) => {
+                    report.duplicates.push(label);
+                    continue;
+                }
+                Overwritten::Left(_, replaced) => report.collisions.push((hash, replaced, l)),
+                Overwritten::Right(old_hash, label) => report.remapped.push((old_hash, label)),
+                Overwritten::Both((_, replaced), (old_hash, label)) => {
+                    report.collisions.push((hash, replaced, l));
+                    report.remapped.push((old_hash, label));
+                }
+            }
+            report.inserted += 1;
+        }
+        report
+    }
+
     /// Inserts labels into the map, providing both the hash and the associated label.
     ///
     /// Users can insert a label for a hash, even if the hash of the label inserted doesn't
     /// match the paired hash. This allows custom descriptive labels when the true label is
     /// not known for the hash.
     pub fn add_custom_labels<I: Iterator<Item = (Hash40, String)>>(&mut self, labels: I) {
+        self.initialized = true;
+        for (hash, label) in labels {
+            self.insert(hash, label);
+        }
+    }
+
+    /// Inserts labels into the map like [`Self::add_custom_labels`], but stops at the first pair
+    /// whose hash or label is already in the map with a different partner, instead of silently
+    /// overwriting it. Pairs before the collision remain inserted.
+    pub fn try_add_custom_labels<I: Iterator<Item = (Hash40, String)>>(
+        &mut self,
+        labels: I,
+    ) -> Result<(), LabelCollisionError> {
+        self.initialized = true;
         for (hash, label) in labels {
-            self.map.insert(hash, label);
+            let existing = match (self.map.get_by_left(&hash), self.map.get_by_right(&label)) {
+                (Some(existing), _) if *existing != label => Some((hash, existing.clone())),
+                (_, Some(existing)) if *existing != hash => Some((*existing, label.clone())),
+                _ => None,
+            };
+            if let Some(existing) = existing {
+                return Err(LabelCollisionError {
+                    new: (hash, label),
+                    existing,
+                });
+            }
+            self.insert(hash, label);
+        }
+        Ok(())
+    }
+
+    /// Inserts every entry of another map into this one. Entries from `other` overwrite any
+    /// colliding entries in this map. The `strict` and `case_insensitive` flags of this map are
+    /// kept, and those of `other` are ignored
+    pub fn merge(&mut self, other: LabelMap) {
+        self.add_custom_labels(other.map.into_iter());
+    }
+
+    /// Inserts a single pair into the map. Any existing pairs with the same hash or the same
+    /// label are removed
+    pub fn insert(&mut self, hash: Hash40, label: String) {
+        self.insert_overwritten(hash, label);
+    }
+
+    /// Inserts a single pair like [`Self::insert`], returning the pairs which were removed
+    fn insert_overwritten(&mut self, hash: Hash40, label: String) -> Overwritten<Hash40, String> {
+        self.initialized = true;
+        let lowercase = self.case_insensitive.then(|| label.to_lowercase());
+        let overwritten = self.map.insert(hash, label);
+        if !self.lowercase_labels.is_empty() || !self.crc_index.is_empty() {
+            match &overwritten {
+                Overwritten::Neither => {}
+                Overwritten::Left(old_hash, old_label)
+                | Overwritten::Right(old_hash, old_label)
+                | Overwritten::Pair(old_hash, old_label) => self.unindex(*old_hash, old_label),
+                Overwritten::Both((hash1, label1), (hash2, label2)) => {
+                    self.unindex(*hash1, label1);
+                    self.unindex(*hash2, label2);
+                }
+            }
+        }
+        if let Some(lowercase) = lowercase {
+            self.lowercase_labels
+                .entry(lowercase)
+                .or_default()
+                .push(hash);
+        }
+        if self.build_crc_index {
+            self.crc_index.entry(hash.crc()).or_default().push(hash);
+        }
+        overwritten
+    }
+
+    /// Removes the label of a hash from the map, returning the label if there was one
+    pub fn remove(&mut self, hash: Hash40) -> Option<String> {
+        let (hash, label) = self.map.remove_by_left(&hash)?;
+        self.unindex(hash, &label);
+        Some(label)
+    }
+
+    /// Removes a pair which is no longer in the map from the secondary indexes
+    fn unindex(&mut self, hash: Hash40, label: &str) {
+        if !self.lowercase_labels.is_empty() {
+            let lowercase = label.to_lowercase();
+            if let Some(hashes) = self.lowercase_labels.get_mut(&lowercase) {
+                hashes.retain(|indexed| *indexed != hash);
+                if hashes.is_empty() {
+                    self.lowercase_labels.remove(&lowercase);
+                }
+            }
+        }
+        if let Some(hashes) = self.crc_index.get_mut(&hash.crc()) {
+            hashes.retain(|indexed| *indexed != hash);
+            if hashes.is_empty() {
+                self.crc_index.remove(&hash.crc());
+            }
         }
     }
 
     /// Opens a file and returns a list of newline-separated labels
     pub fn read_labels<P: AsRef<Path>>(path: P) -> Result<Vec<String>, io::Error> {
-        let reader = BufReader::new(File::open(path)?);
-        reader.lines().collect()
+        Self::read_labels_from(BufReader::new(File::open(path)?))
+    }
+
+    /// Returns a list of newline-separated labels from a reader. Blank lines and lines starting
+    /// with `#` are skipped
+    pub fn read_labels_from<R: BufRead>(reader: R) -> Result<Vec<String>, io::Error> {
+        reader
+            .lines()
+            .filter(|line_result| !matches!(line_result, Ok(line) if Self::is_skipped(line)))
+            .collect()
+    }
+
+    /// Returns whether a line of a label file is blank or a comment
+    fn is_skipped(line: &str) -> bool {
+        let line = line.trim();
+        line.is_empty() || line.starts_with('#')
     }
 
     /// Opens a file and returns a list of line-separated pairs of hashes and labels.
-    /// Each hash-label pair is separated by a comma, and the hash must be formatted
-    /// in hexadecimal, beginning with "0x"
+    /// Each hash-label pair is separated by the first comma, and the hash must be formatted
+    /// in hexadecimal, beginning with "0x". Any further commas are kept in the label. Blank
+    /// lines and lines starting with `#` are skipped
     pub fn read_custom_labels<P: AsRef<Path>>(
         path: P,
     ) -> Result<Vec<(Hash40, String)>, CustomLabelError> {
-        let reader = BufReader::new(File::open(path)?);
+        Self::read_custom_labels_with_options(path, &CustomLabelOptions::default())
+    }
+
+    /// Opens a file and returns a list of line-separated pairs of hashes and labels, with the
+    /// columns laid out as described by the options. The hash must be formatted in
+    /// hexadecimal, beginning with "0x"
+    pub fn read_custom_labels_with_options<P: AsRef<Path>>(
+        path: P,
+        options: &CustomLabelOptions,
+    ) -> Result<Vec<(Hash40, String)>, CustomLabelError> {
+        options.validate()?;
+        #[cfg(feature = "rayon")]
+        let labels = Self::par_parse_custom_labels(&std::fs::read_to_string(path)?, options);
+        #[cfg(not(feature = "rayon"))]
+        let labels =
+            Self::read_custom_labels_from_with_options(BufReader::new(File::open(path)?), options);
+        labels
+    }
+
+    /// Returns a list of line-separated pairs of hashes and labels from a reader, in the
+    /// same format as [`Self::read_custom_labels`]
+    pub fn read_custom_labels_from<R: BufRead>(
+        reader: R,
+    ) -> Result<Vec<(Hash40, String)>, CustomLabelError> {
+        Self::read_custom_labels_from_with_options(reader, &CustomLabelOptions::default())
+    }
+
+    /// Returns a list of line-separated pairs of hashes and labels from a reader, in the
+    /// same format as [`Self::read_custom_labels_with_options`]
+    pub fn read_custom_labels_from_with_options<R: BufRead>(
+        reader: R,
+        options: &CustomLabelOptions,
+    ) -> Result<Vec<(Hash40, String)>, CustomLabelError> {
+        options.validate()?;
         reader
             .lines()
-            .map(|line_result| {
-                let line = line_result?;
-                let mut split = line.split(',');
-                split
-                    .next()
-                    .zip(split.next())
-                    .ok_or(CustomLabelError::MisingColumn)
-                    .and_then(|(hash, label)| {
-                        Ok((Hash40::from_hex_str(hash)?, String::from(label)))
-                    })
-            })
+            .enumerate()
+            .filter(|(_, line_result)| !matches!(line_result, Ok(line) if Self::is_skipped(line)))
+            .map(|(index, line_result)| Self::parse_custom_label(index + 1, &line_result?, options))
             .collect()
     }
 
+    #[cfg(feature = "rayon")]
+    /// Returns a list of line-separated pairs of hashes and labels from a string, in the
+    /// same format as [`Self::read_custom_labels`]. The lines are parsed in parallel
+    pub fn par_read_custom_labels_from_str(
+        text: &str,
+    ) -> Result<Vec<(Hash40, String)>, CustomLabelError> {
+        Self::par_parse_custom_labels(text, &CustomLabelOptions::default())
+    }
+
+    #[cfg(feature = "rayon")]
+    fn par_parse_custom_labels(
+        text: &str,
+        options: &CustomLabelOptions,
+    ) -> Result<Vec<(Hash40, String)>, CustomLabelError> {
+        text.lines()
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .enumerate()
+            .filter(|(_, text)| !Self::is_skipped(text))
+            .map(|(index, text)| Self::parse_custom_label(index + 1, text, options))
+            .collect()
+    }
+
+    fn parse_custom_label(
+        line: usize,
+        text: &str,
+        options: &CustomLabelOptions,
+    ) -> Result<(Hash40, String), CustomLabelError> {
+        // the label keeps any further delimiters only when it's the last column
+        let columns = if options.label_column > options.hash_column {
+            text.splitn(options.label_column + 1, options.delimiter)
+                .collect::<Vec<_>>()
+        } else {
+            text.split(options.delimiter).collect::<Vec<_>>()
+        };
+        columns
+            .get(options.hash_column)
+            .zip(columns.get(options.label_column))
+            .ok_or(CustomLabelError::MissingColumn { line })
+            .and_then(|(hash, label)| {
+                Hash40::from_hex_str(hash)
+                    .map(|hash| (hash, String::from(*label)))
+                    .map_err(|error| CustomLabelError::ParseHashError { line, error })
+            })
+    }
+
+    /// Writes every label in the map to a file, one per line, sorted alphabetically.
+    /// The output can be read back with [`Self::read_labels`]
+    pub fn save_labels<P: AsRef<Path>>(&self, path: P) -> Result<(), io::Error> {
+        let mut labels = self.map.right_values().collect::<Vec<_>>();
+        labels.sort();
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        for label in labels {
+            writeln!(writer, "{}", label)?;
+        }
+        writer.flush()
+    }
+
+    /// Writes every hash-label pair in the map to a file, one per line, sorted by hash.
+    /// The output can be read back with [`Self::read_custom_labels`]
+    pub fn save_custom_labels<P: AsRef<Path>>(&self, path: P) -> Result<(), io::Error> {
+        let mut pairs = self.map.iter().collect::<Vec<_>>();
+        pairs.sort();
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        for (hash, label) in pairs {
+            writeln!(writer, "{},{}", hash.to_hex_string(), label)?;
+        }
+        writer.flush()
+    }
+
+    #[cfg(feature = "embed-labels")]
+    /// Inserts the labels embedded into the crate at build time. By default this is a small
+    /// list of common labels, which can be replaced by setting the `HASH40_LABELS` environment
+    /// variable to the path of a newline-separated label file when building. A relative path is
+    /// resolved against the directory of the hash40 package, not the crate depending on it, so
+    /// an absolute path is usually needed
+    pub fn add_embedded_labels(&mut self) {
+        self.add_labels(
+            EMBEDDED_LABELS
+                .lines()
+                .filter(|line| !Self::is_skipped(line))
+                .map(String::from),
+        );
+    }
+
     /// A combination of the two functions [`Self::add_labels`] and [`Self::read_labels`]
     pub fn add_labels_from_path<P: AsRef<Path>>(&mut self, path: P) -> Result<(), io::Error> {
         self.add_labels(Self::read_labels(path)?);
         Ok(())
     }
 
+    #[cfg(feature = "flate2")]
+    /// The same as [`Self::add_labels_from_path`], but for a gzip-compressed file
+    pub fn add_labels_from_gz_path<P: AsRef<Path>>(&mut self, path: P) -> Result<(), io::Error> {
+        let reader = BufReader::new(GzDecoder::new(File::open(path)?));
+        self.add_labels(Self::read_labels_from(reader)?);
+        Ok(())
+    }
+
+    #[cfg(feature = "flate2")]
+    /// The same as [`Self::add_custom_labels_from_path`], but for a gzip-compressed file
+    pub fn add_custom_labels_from_gz_path<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> Result<(), CustomLabelError> {
+        let reader = BufReader::new(GzDecoder::new(File::open(path)?));
+        self.add_custom_labels(Self::read_custom_labels_from(reader)?.into_iter());
+        Ok(())
+    }
+
+    #[cfg(feature = "ndjson")]
+    /// Inserts hash-label pairs from a reader of newline-delimited JSON, where each line is an
+    /// object like `{"hash":"0x0123456789","label":"..."}`. Blank lines are skipped. If any line
+    /// fails to parse, no pairs are inserted
+    pub fn add_labels_from_ndjson<R: BufRead>(
+        &mut self,
+        reader: R,
+    ) -> Result<(), CustomLabelError> {
+        let labels = reader
+            .lines()
+            .enumerate()
+            .filter(|(_, line_result)| !matches!(line_result, Ok(line) if line.trim().is_empty()))
+            .map(|(index, line_result)| {
+                let line = index + 1;
+                let pair = serde_json::from_str::<NdjsonLabel>(&line_result?)
+                    .map_err(|error| CustomLabelError::Json { line, error })?;
+                Hash40::from_hex_str(&pair.hash)
+                    .map(|hash| (hash, pair.label))
+                    .map_err(|error| CustomLabelError::ParseHashError { line, error })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.add_custom_labels(labels.into_iter());
+        Ok(())
+    }
+
     /// A combination of the two functions [`Self::add_custom_labels`] and
     /// [`Self::read_custom_labels`]
     pub fn add_custom_labels_from_path<P: AsRef<Path>>(
@@ -95,26 +665,1276 @@ impl LabelMap {
         Ok(())
     }
 
+    /// Returns an owned copy of the label of a hash. Use [`Self::get_label`] to borrow the label
+    /// from the map without allocating
     pub fn label_of(&self, hash: Hash40) -> Option<String> {
         self.map.get_by_left(&hash).map(Into::into)
     }
 
+    /// Finds the longest prefix of a `/`-separated path which is a label in the map, ending at a
+    /// separator or at the end of the path. Returns the prefix and its hash.
+    ///
+    /// This works on label text rather than hashes, since a hash made with
+    /// [`Hash40::join_path`] can't be split back into the hashes of its components
+    pub fn longest_prefix_match<'a>(&self, path: &'a str) -> Option<(&'a str, Hash40)> {
+        let ends = path
+            .match_indices('/')
+            .map(|(index, _)| index)
+            .chain(std::iter::once(path.len()));
+        ends.rev().find_map(|end| {
+            let prefix = &path[..end];
+            self.get_hash(prefix).map(|hash| (prefix, hash))
+        })
+    }
+
+    /// Returns every pair in the map whose label doesn't hash to its paired hash, sorted by
+    /// hash. These are either descriptive custom labels, or mistakes in a label file
+    pub fn validate(&self) -> Vec<(Hash40, String)> {
+        let mut mismatches = self
+            .map
+            .iter()
+            .filter(|(hash, label)| hash40(label) != **hash)
+            .map(|(hash, label)| (*hash, label.clone()))
+            .collect::<Vec<_>>();
+        mismatches.sort();
+        mismatches
+    }
+
+    /// Returns every pair in the map whose hash has the given CRC32, regardless of the length
+    /// stored in the hash, sorted by hash. This uses the CRC index while `build_crc_index` is
+    /// set, and searches the whole map otherwise
+    pub fn find_by_crc(&self, crc: u32) -> Vec<(Hash40, &String)> {
+        let mut pairs = if self.build_crc_index {
+            self.crc_index
+                .get(&crc)
+                .into_iter()
+                .flatten()
+                .filter_map(|hash| self.map.get_by_left(hash).map(|label| (*hash, label)))
+                .collect::<Vec<_>>()
+        } else {
+            self.map
+                .iter()
+                .filter(|(hash, _)| hash.crc() == crc)
+                .map(|(hash, label)| (*hash, label))
+                .collect::<Vec<_>>()
+        };
+        pairs.sort();
+        pairs
+    }
+
+    /// Compares two hashes by their labels. Labelled hashes are ordered before unlabelled ones,
+    /// and two unlabelled hashes are compared by their numeric value
+    pub fn cmp_by_label(&self, a: Hash40, b: Hash40) -> Ordering {
+        match (self.map.get_by_left(&a), self.map.get_by_left(&b)) {
+            (Some(a), Some(b)) => a.cmp(b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => a.cmp(&b),
+        }
+    }
+
+    /// Finds the labels of many hashes at once. See [`Self::label_of`]
+    pub fn labels_of<I: IntoIterator<Item = Hash40>>(&self, hashes: I) -> Vec<Option<String>> {
+        hashes.into_iter().map(|hash| self.label_of(hash)).collect()
+    }
+
+    /// Returns the label of a hash, borrowed from the map. For the static label map, the borrow
+    /// is valid while the lock is held, or within [`Hash40::with_label_map`]
+    pub fn get_label(&self, hash: Hash40) -> Option<&str> {
+        self.map.get_by_left(&hash).map(String::as_str)
+    }
+
+    /// Returns whether the map contains a label for the hash
+    pub fn contains_hash(&self, hash: Hash40) -> bool {
+        self.map.contains_left(&hash)
+    }
+
+    /// Returns whether the map contains the label. This ignores the `case_insensitive` flag
+    pub fn contains_label(&self, label: &str) -> bool {
+        self.map.contains_right(label)
+    }
+
+    /// Returns an iterator over every hash-label pair in the map, in arbitrary order
+    pub fn iter(&self) -> Iter<'_> {
+        self.into_iter()
+    }
+
+    /// Returns an iterator over every label in the map, in arbitrary order
+    pub fn labels(&self) -> impl Iterator<Item = &str> + '_ {
+        self.map.right_values().map(String::as_str)
+    }
+
+    /// Returns an iterator over every hash in the map, in arbitrary order
+    pub fn hashes(&self) -> impl Iterator<Item = Hash40> + '_ {
+        self.map.left_values().copied()
+    }
+
+    /// Finds the hash of a label. If the label isn't in the map, the label is hashed with
+    /// [`Hash40::new`] unless the map is strict, in which case `None` is returned
     pub fn hash_of(&self, label: &str) -> Option<Hash40> {
+        self.get_hash(label)
+            .or_else(|| (!self.strict).then(|| hash40(label)))
+    }
+
+    /// Finds the hash of a label like [`Self::hash_of`], but returns
+    /// [`FromLabelError::LabelNotFound`] instead of `None` when the map is strict and doesn't
+    /// contain the label
+    pub fn try_hash_of(&self, label: &str) -> Result<Hash40, FromLabelError> {
+        self.hash_of(label)
+            .ok_or_else(|| FromLabelError::LabelNotFound(String::from(label)))
+    }
+
+    /// Searches the map for the hash of a label. Unlike [`Self::hash_of`], this never falls back
+    /// to hashing the label
+    pub fn get_hash(&self, label: &str) -> Option<Hash40> {
         self.map
             .get_by_right(label)
+            .or_else(|| {
+                self.case_insensitive
+                    .then(|| self.lowercase_labels.get(&label.to_lowercase()))
+                    .flatten()
+                    .and_then(|hashes| hashes.last())
+            })
             .copied()
-            .or_else(|| (!self.strict).then(|| hash40(label)))
     }
 }
 
+impl LabelMapBuilder {
+    /// Adds a file of newline-separated labels to load. See [`LabelMap::read_labels`]
+    pub fn labels_from_path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.sources
+            .push(LabelSource::Labels(path.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Adds a file of hash-label pairs to load. See [`LabelMap::read_custom_labels`]
+    pub fn custom_labels_from_path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.sources
+            .push(LabelSource::CustomLabels(path.as_ref().to_path_buf()));
+        self
+    }
+
+    /// Creates a builder for the label file named by the [`LABEL_FILE_ENV`] environment
+    /// variable, or returns `None` if it isn't set. Files with a `.csv` extension are read as
+    /// custom labels, and any other file as newline-separated labels. The `strict` flag is read
+    /// from [`STRICT_ENV`]
+    pub fn from_env() -> Option<Self> {
+        Self::from_vars(|name| std::env::var_os(name))
+    }
+
+    fn from_vars<F: Fn(&str) -> Option<OsString>>(var: F) -> Option<Self> {
+        let path = PathBuf::from(var(LABEL_FILE_ENV)?);
+        let strict = var(STRICT_ENV).is_some_and(|value| {
+            value.eq_ignore_ascii_case("1") || value.eq_ignore_ascii_case("true")
+        });
+        let is_custom = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+        let builder = Self::default().strict(strict);
+        Some(if is_custom {
+            builder.custom_labels_from_path(path)
+        } else {
+            builder.labels_from_path(path)
+        })
+    }
+
+    /// Sets the `strict` flag of the map
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Loads every file, in the order they were added, into a new map
+    pub fn build(self) -> Result<LabelMap, CustomLabelError> {
+        let mut map = LabelMap {
+            strict: self.strict,
+            ..Default::default()
+        };
+        for source in self.sources {
+            match source {
+                LabelSource::Labels(path) => map.add_labels_from_path(path)?,
+                LabelSource::CustomLabels(path) => map.add_custom_labels_from_path(path)?,
+            }
+        }
+        Ok(map)
+    }
+
+    /// Builds the map, then replaces the static label map with it. If any file fails to load,
+    /// the static label map is left unchanged
+    pub fn install(self) -> Result<(), CustomLabelError> {
+        let map = self.build()?;
+        *write_labels() = map;
+        Ok(())
+    }
+}
+
+impl Extend<(Hash40, String)> for LabelMap {
+    fn extend<I: IntoIterator<Item = (Hash40, String)>>(&mut self, iter: I) {
+        self.add_custom_labels(iter.into_iter());
+    }
+}
+
+impl FromIterator<String> for LabelMap {
+    fn from_iter<I: IntoIterator<Item = String>>(iter: I) -> Self {
+        let mut map = Self::default();
+        map.add_labels(iter);
+        map
+    }
+}
+
+impl FromIterator<(Hash40, String)> for LabelMap {
+    fn from_iter<I: IntoIterator<Item = (Hash40, String)>>(iter: I) -> Self {
+        let mut map = Self::default();
+        map.add_custom_labels(iter.into_iter());
+        map
+    }
+}
+
+impl<'a> IntoIterator for &'a LabelMap {
+    type Item = (Hash40, &'a str);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Iter {
+            inner: self.map.iter(),
+        }
+    }
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (Hash40, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next()
+            .map(|(hash, label)| (*hash, label.as_str()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl CustomLabelOptions {
+    /// Rejects options which read the hash and label from the same column
+    fn validate(&self) -> Result<(), CustomLabelError> {
+        if self.hash_column == self.label_column {
+            return Err(CustomLabelError::SameColumn {
+                column: self.hash_column,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Default for CustomLabelOptions {
+    fn default() -> Self {
+        Self {
+            hash_column: 0,
+            label_column: 1,
+            delimiter: ',',
+        }
+    }
+}
+
+/// Diffs the hash-label pairs of two maps. The flags of the maps are not compared, and applying
+/// a diff keeps the flags of the map it is applied to
+impl Diff for LabelMap {
+    type Repr = LabelMapDiff;
+
+    fn diff(&self, other: &Self) -> Self::Repr {
+        let mut diff = LabelMapDiff::default();
+        for (hash, label) in &self.map {
+            match other.map.get_by_left(hash) {
+                Some(other_label) if other_label != label => {
+                    diff.changed.push((*hash, other_label.clone()))
+                }
+                Some(_) => {}
+                None => diff.removed.push(*hash),
+            }
+        }
+        diff.added.extend(
+            other
+                .map
+                .iter()
+                .filter(|(hash, _)| !self.map.contains_left(hash))
+                .map(|(hash, label)| (*hash, label.clone())),
+        );
+        diff.added.sort();
+        diff.removed.sort();
+        diff.changed.sort();
+        diff
+    }
+
+    fn apply(&mut self, diff: &Self::Repr) {
+        for hash in &diff.removed {
+            self.remove(*hash);
+        }
+        for (hash, label) in diff.changed.iter().chain(&diff.added) {
+            self.insert(*hash, label.clone());
+        }
+    }
+
+    fn identity() -> Self {
+        Default::default()
+    }
+}
+
+/// Serializes the map as a struct with a `strict` field and a `labels` field, which maps
+/// hexadecimal hashes to their labels. The `case_insensitive` flag is not serialized
+#[cfg(feature = "serde")]
+impl Serialize for LabelMap {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("LabelMap", 2)?;
+        state.serialize_field("strict", &self.strict)?;
+        state.serialize_field("labels", &SerializeLabels(self))?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+/// Serializes the pairs of a map sorted by hash, so that the output is deterministic
+struct SerializeLabels<'a>(&'a LabelMap);
+
+#[cfg(feature = "serde")]
+impl Serialize for SerializeLabels<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut pairs = self.0.map.iter().collect::<Vec<_>>();
+        pairs.sort();
+
+        let mut map = serializer.serialize_map(Some(pairs.len()))?;
+        for (hash, label) in pairs {
+            map.serialize_entry(&hash.to_hex_string(), label)?;
+        }
+        map.end()
+    }
+}
+
+/// Deserializes the map from the format written by its `Serialize` implementation. A hash or
+/// label which appears more than once is an error, rather than overwriting the earlier pair
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for LabelMap {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_struct("LabelMap", LABEL_MAP_FIELDS, LabelMapVisitor)
+    }
+}
+
+#[cfg(feature = "serde")]
+const LABEL_MAP_FIELDS: &[&str] = &["strict", "labels"];
+
+#[cfg(feature = "serde")]
+/// Used to implement serde's Deserialize trait
+struct LabelMapVisitor;
+
+#[cfg(feature = "serde")]
+impl<'de> de::Visitor<'de> for LabelMapVisitor {
+    type Value = LabelMap;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("A label map with strict and labels fields")
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let strict = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let DeserializeLabels(mut map) = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        map.strict = strict;
+        Ok(map)
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+        let mut strict = None;
+        let mut labels = None;
+        while let Some(key) = access.next_key::<String>()? {
+            match key.as_str() {
+                "strict" if strict.is_some() => return Err(de::Error::duplicate_field("strict")),
+                "strict" => strict = Some(access.next_value()?),
+                "labels" if labels.is_some() => return Err(de::Error::duplicate_field("labels")),
+                "labels" => labels = Some(access.next_value::<DeserializeLabels>()?),
+                _ => return Err(de::Error::unknown_field(&key, LABEL_MAP_FIELDS)),
+            }
+        }
+        let DeserializeLabels(mut map) =
+            labels.ok_or_else(|| de::Error::missing_field("labels"))?;
+        map.strict = strict.unwrap_or_default();
+        Ok(map)
+    }
+}
+
+#[cfg(feature = "serde")]
+/// Deserializes the `labels` field of a serialized map
+struct DeserializeLabels(LabelMap);
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for DeserializeLabels {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_map(DeserializeLabelsVisitor)
+    }
+}
+
+#[cfg(feature = "serde")]
+/// Used to implement serde's Deserialize trait
+struct DeserializeLabelsVisitor;
+
+#[cfg(feature = "serde")]
+impl<'de> de::Visitor<'de> for DeserializeLabelsVisitor {
+    type Value = DeserializeLabels;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("A map with hexadecimal hashes as keys and labels as values")
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+        let mut map = LabelMap {
+            initialized: true,
+            ..Default::default()
+        };
+        while let Some((hash, label)) = access.next_entry::<String, String>()? {
+            let hash = Hash40::from_hex_str(&hash).map_err(de::Error::custom)?;
+            if map.contains_hash(hash) {
+                return Err(de::Error::custom(format!(
+                    "duplicate hash {}",
+                    hash.to_hex_string()
+                )));
+            }
+            if map.contains_label(&label) {
+                return Err(de::Error::custom(format!("duplicate label \"{}\"", label)));
+            }
+            map.insert(hash, label);
+        }
+        Ok(DeserializeLabels(map))
+    }
+}
+
+impl Display for CustomLabelError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{}", err),
+            Self::MissingColumn { line } => write!(f, "missing label column on line {}", line),
+            Self::SameColumn { column } => {
+                write!(
+                    f,
+                    "the hash and label can't both be read from column {}",
+                    column
+                )
+            }
+            Self::ParseHashError { line, error } => write!(f, "{} on line {}", error, line),
+            #[cfg(feature = "ndjson")]
+            Self::Json { line, error } => write!(f, "{} on line {}", error, line),
+        }
+    }
+}
+
+impl Error for CustomLabelError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::MissingColumn { .. } | Self::SameColumn { .. } => None,
+            Self::ParseHashError { error, .. } => Some(error),
+            #[cfg(feature = "ndjson")]
+            Self::Json { error, .. } => Some(error),
+        }
+    }
+}
+
+/// Converts the error into an [`io::Error`]. I/O errors are returned as they were, and any
+/// other error becomes the source of an error of kind [`io::ErrorKind::InvalidData`]
+impl From<CustomLabelError> for io::Error {
+    fn from(err: CustomLabelError) -> Self {
+        match err {
+            CustomLabelError::Io(err) => err,
+            err => io::Error::new(io::ErrorKind::InvalidData, err),
+        }
+    }
+}
+
+impl Display for LabelCollisionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "cannot insert {} => \"{}\" without overwriting {} => \"{}\"",
+            self.new.0.to_hex_string(),
+            self.new.1,
+            self.existing.0.to_hex_string(),
+            self.existing.1
+        )
+    }
+}
+
+impl Error for LabelCollisionError {}
+
 impl From<io::Error> for CustomLabelError {
     fn from(err: io::Error) -> Self {
         Self::Io(err)
     }
 }
 
-impl From<ParseHashError> for CustomLabelError {
-    fn from(err: ParseHashError) -> Self {
-        Self::ParseHashError(err)
+#[test]
+fn test_contains_hash() {
+    let labels = labels! { "fighter" };
+    assert!(labels.contains_hash(hash40("fighter")));
+    assert!(!labels.contains_hash(hash40("stage")));
+}
+
+#[test]
+fn test_save_labels() {
+    let dir = std::env::temp_dir();
+    let labels_path = dir.join("hash40_test_save_labels.txt");
+    let custom_path = dir.join("hash40_test_save_custom_labels.csv");
+
+    let mut labels = LabelMap::default();
+    labels.add_labels(vec![String::from("stage"), String::from("fighter")]);
+    labels.add_custom_labels(vec![(Hash40(0x1234), String::from("custom"))].into_iter());
+
+    labels.save_labels(&labels_path).unwrap();
+    assert_eq!(
+        LabelMap::read_labels(&labels_path).unwrap(),
+        vec!["custom", "fighter", "stage"]
+    );
+
+    labels.save_custom_labels(&custom_path).unwrap();
+    let mut loaded = LabelMap::default();
+    loaded.add_custom_labels_from_path(&custom_path).unwrap();
+    assert_eq!(loaded.map, labels.map);
+    assert_eq!(loaded.len(), 3);
+
+    std::fs::remove_file(labels_path).unwrap();
+    std::fs::remove_file(custom_path).unwrap();
+}
+
+#[test]
+fn test_read_from_reader() {
+    let labels = LabelMap::read_labels_from(&b"fighter\nstage"[..]).unwrap();
+    assert_eq!(labels, vec!["fighter", "stage"]);
+
+    let custom = LabelMap::read_custom_labels_from(&b"0x1234,custom\n0x0aa3cb8810,x"[..]).unwrap();
+    assert_eq!(
+        custom,
+        vec![
+            (Hash40(0x1234), String::from("custom")),
+            (Hash40(0x0aa3cb8810), String::from("x"))
+        ]
+    );
+}
+
+#[test]
+fn test_read_custom_labels_with_commas() {
+    let custom = LabelMap::read_custom_labels_from(&b"0x1234,effect,foo,bar"[..]).unwrap();
+    assert_eq!(
+        custom,
+        vec![(Hash40(0x1234), String::from("effect,foo,bar"))]
+    );
+}
+
+#[test]
+fn test_custom_label_error_lines() {
+    let missing = LabelMap::read_custom_labels_from(&b"0x1234,a\n0x5678"[..]);
+    assert!(matches!(
+        missing,
+        Err(CustomLabelError::MissingColumn { line: 2 })
+    ));
+
+    let invalid = LabelMap::read_custom_labels_from(&b"0x1234,a\n0x5678,b\n1234,c"[..]);
+    assert!(matches!(
+        invalid,
+        Err(CustomLabelError::ParseHashError {
+            line: 3,
+            error: ParseHashError::MissingPrefix
+        })
+    ));
+}
+
+#[test]
+fn test_is_initialized() {
+    let mut labels = LabelMap::default();
+    assert!(!labels.is_initialized());
+    labels.add_labels(vec![]);
+    assert!(labels.is_initialized());
+    labels.clear();
+    assert!(labels.is_initialized());
+}
+
+#[test]
+fn test_len() {
+    let mut labels = LabelMap::default();
+    assert!(labels.is_empty());
+    labels.add_labels(vec![String::from("fighter"), String::from("stage")]);
+    assert_eq!(labels.len(), 2);
+    assert!(!labels.is_empty());
+}
+
+#[test]
+fn test_case_insensitive() {
+    let mut labels = LabelMap::default()
+        .with_strict(true)
+        .with_case_insensitive(true);
+    labels.add_labels(vec![String::from("Fighter")]);
+    labels.add_custom_labels(vec![(Hash40(0x1234), String::from("CustomLabel"))].into_iter());
+
+    assert_eq!(labels.hash_of("fighter"), Some(hash40("Fighter")));
+    assert_eq!(labels.hash_of("FIGHTER"), Some(hash40("Fighter")));
+    assert_eq!(labels.hash_of("customlabel"), Some(Hash40(0x1234)));
+    assert_eq!(
+        labels.label_of(hash40("fighter")),
+        Some(String::from("Fighter"))
+    );
+
+    labels.add_custom_labels(vec![(Hash40(0x1234), String::from("Other"))].into_iter());
+    assert_eq!(labels.hash_of("customlabel"), None);
+    assert_eq!(labels.hash_of("other"), Some(Hash40(0x1234)));
+
+    labels.set_case_insensitive(false);
+    assert_eq!(labels.hash_of("fighter"), None);
+}
+
+#[test]
+fn test_case_insensitive_enabled_after_loading() {
+    let mut labels = labels! { "Fighter" }.with_strict(true);
+    assert_eq!(labels.get_hash("fighter"), None);
+
+    labels.set_case_insensitive(true);
+    assert!(labels.is_case_insensitive());
+    assert_eq!(labels.get_hash("fighter"), Some(hash40("Fighter")));
+    assert_eq!(labels.hash_of("FIGHTER"), Some(hash40("Fighter")));
+
+    labels.set_case_insensitive(false);
+    assert!(labels.lowercase_labels.is_empty());
+}
+
+#[test]
+fn test_case_insensitive_shared_key() {
+    let mut labels = LabelMap::default()
+        .with_strict(true)
+        .with_case_insensitive(true);
+    labels.insert(Hash40(0x1), String::from("Foo"));
+    labels.insert(Hash40(0x2), String::from("FOO"));
+    assert_eq!(labels.hash_of("foo"), Some(Hash40(0x2)));
+
+    labels.remove(Hash40(0x2));
+    assert_eq!(labels.hash_of("foo"), Some(Hash40(0x1)));
+
+    labels.remove(Hash40(0x1));
+    assert_eq!(labels.hash_of("foo"), None);
+    assert!(labels.lowercase_labels.is_empty());
+}
+
+#[test]
+fn test_labels_of() {
+    let labels = labels! { "fighter" };
+    assert_eq!(
+        labels.labels_of(vec![hash40("fighter"), hash40("stage")]),
+        vec![Some(String::from("fighter")), None]
+    );
+}
+
+#[test]
+#[cfg(feature = "flate2")]
+fn test_add_labels_from_gz_path() {
+    use flate2::{write::GzEncoder, Compression};
+
+    fn write_gz(path: &Path, contents: &[u8]) {
+        let mut encoder = GzEncoder::new(File::create(path).unwrap(), Compression::default());
+        encoder.write_all(contents).unwrap();
+        encoder.finish().unwrap();
+    }
+
+    let dir = std::env::temp_dir();
+    let labels_path = dir.join("hash40_test_labels.txt.gz");
+    let custom_path = dir.join("hash40_test_custom_labels.csv.gz");
+    write_gz(&labels_path, b"fighter\nstage");
+    write_gz(&custom_path, b"0x1234,custom");
+
+    let mut labels = LabelMap::default();
+    labels.add_labels_from_gz_path(&labels_path).unwrap();
+    labels.add_custom_labels_from_gz_path(&custom_path).unwrap();
+    assert_eq!(
+        labels.label_of(hash40("fighter")),
+        Some(String::from("fighter"))
+    );
+    assert_eq!(
+        labels.label_of(hash40("stage")),
+        Some(String::from("stage"))
+    );
+    assert_eq!(
+        labels.label_of(Hash40(0x1234)),
+        Some(String::from("custom"))
+    );
+
+    std::fs::remove_file(labels_path).unwrap();
+    std::fs::remove_file(custom_path).unwrap();
+}
+
+#[test]
+#[cfg(feature = "rayon")]
+fn test_par_read_custom_labels() {
+    let text = (0..1000)
+        .map(|n| format!("0x{:010x},label_{}", n, n))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let sequential = LabelMap::read_custom_labels_from(text.as_bytes()).unwrap();
+    let parallel = LabelMap::par_read_custom_labels_from_str(&text).unwrap();
+    assert_eq!(sequential, parallel);
+
+    let mut sequential_map = LabelMap::default();
+    sequential_map.add_custom_labels(sequential.into_iter());
+    let mut parallel_map = LabelMap::default();
+    parallel_map.add_custom_labels(parallel.into_iter());
+    assert_eq!(sequential_map.map, parallel_map.map);
+
+    assert!(matches!(
+        LabelMap::par_read_custom_labels_from_str("0x1234,a\nbad"),
+        Err(CustomLabelError::MissingColumn { line: 2 })
+    ));
+}
+
+#[test]
+fn test_merge() {
+    let mut base = labels! { "fighter" };
+    base.add_custom_labels(vec![(Hash40(0x1234), String::from("old"))].into_iter());
+
+    let mut overrides = LabelMap {
+        strict: true,
+        ..Default::default()
+    };
+    overrides.add_custom_labels(vec![(Hash40(0x1234), String::from("new"))].into_iter());
+
+    base.merge(overrides);
+    base.extend(vec![(Hash40(0x5678), String::from("extended"))]);
+    assert!(!base.strict);
+
+    let mut pairs = (&base).into_iter().collect::<Vec<_>>();
+    pairs.sort();
+    assert_eq!(
+        pairs,
+        vec![
+            (Hash40(0x1234), "new"),
+            (Hash40(0x5678), "extended"),
+            (hash40("fighter"), "fighter"),
+        ]
+    );
+}
+
+#[test]
+fn test_try_add_custom_labels() {
+    let mut labels = LabelMap::default();
+    let pairs = vec![
+        (Hash40(0x1234), String::from("a")),
+        (Hash40(0x1234), String::from("a")),
+        (Hash40(0x5678), String::from("b")),
+    ];
+    assert_eq!(labels.try_add_custom_labels(pairs.into_iter()), Ok(()));
+
+    assert_eq!(
+        labels.try_add_custom_labels(vec![(Hash40(0x1234), String::from("c"))].into_iter()),
+        Err(LabelCollisionError {
+            new: (Hash40(0x1234), String::from("c")),
+            existing: (Hash40(0x1234), String::from("a")),
+        })
+    );
+    assert_eq!(
+        labels.try_add_custom_labels(vec![(Hash40(0x9abc), String::from("b"))].into_iter()),
+        Err(LabelCollisionError {
+            new: (Hash40(0x9abc), String::from("b")),
+            existing: (Hash40(0x5678), String::from("b")),
+        })
+    );
+    assert_eq!(labels.label_of(Hash40(0x1234)), Some(String::from("a")));
+    assert_eq!(labels.label_of(Hash40(0x5678)), Some(String::from("b")));
+}
+
+#[test]
+fn test_cmp_by_label() {
+    let mut labels = LabelMap::default();
+    labels.add_custom_labels(
+        vec![
+            (Hash40(1), String::from("b")),
+            (Hash40(2), String::from("a")),
+        ]
+        .into_iter(),
+    );
+
+    let mut hashes = vec![Hash40(4), Hash40(1), Hash40(3), Hash40(2)];
+    hashes.sort_by(|a, b| labels.cmp_by_label(*a, *b));
+    assert_eq!(hashes, vec![Hash40(2), Hash40(1), Hash40(3), Hash40(4)]);
+}
+
+#[test]
+fn test_builder() {
+    let dir = std::env::temp_dir();
+    let labels_path = dir.join("hash40_test_builder_labels.txt");
+    let custom_path = dir.join("hash40_test_builder_custom_labels.csv");
+    std::fs::write(&labels_path, "fighter\nstage").unwrap();
+    std::fs::write(&custom_path, "0x0000001234,custom").unwrap();
+
+    let labels = LabelMapBuilder::default()
+        .labels_from_path(&labels_path)
+        .custom_labels_from_path(&custom_path)
+        .strict(true)
+        .build()
+        .unwrap();
+    assert!(labels.strict);
+    assert_eq!(
+        labels.label_of(hash40("stage")),
+        Some(String::from("stage"))
+    );
+    assert_eq!(
+        labels.label_of(Hash40(0x1234)),
+        Some(String::from("custom"))
+    );
+
+    let missing = LabelMapBuilder::default()
+        .labels_from_path(dir.join("hash40_test_builder_missing.txt"))
+        .build();
+    assert!(matches!(missing, Err(CustomLabelError::Io(_))));
+
+    std::fs::remove_file(labels_path).unwrap();
+    std::fs::remove_file(custom_path).unwrap();
+}
+
+#[test]
+fn test_accessors() {
+    let mut labels = LabelMap::default();
+    labels.insert(Hash40(0x1234), String::from("custom"));
+    labels.insert(hash40("fighter"), String::from("fighter"));
+
+    assert_eq!(labels.get_label(Hash40(0x1234)), Some("custom"));
+    assert_eq!(labels.get_hash("custom"), Some(Hash40(0x1234)));
+    assert_eq!(labels.get_hash("stage"), None);
+    assert!(labels.contains_label("fighter"));
+    assert_eq!(labels.iter().count(), 2);
+
+    assert_eq!(labels.remove(Hash40(0x1234)), Some(String::from("custom")));
+    assert_eq!(labels.remove(Hash40(0x1234)), None);
+    assert!(!labels.contains_label("custom"));
+    assert_eq!(
+        labels.iter().collect::<Vec<_>>(),
+        vec![(hash40("fighter"), "fighter")]
+    );
+    assert_eq!(labels.labels().collect::<Vec<_>>(), vec!["fighter"]);
+    assert_eq!(labels.hashes().collect::<Vec<_>>(), vec![hash40("fighter")]);
+}
+
+#[test]
+fn test_validate() {
+    let mut labels = labels! { "fighter" };
+    labels.insert(Hash40(0x5678), String::from("typo"));
+    labels.insert(Hash40(0x1234), String::from("custom"));
+    assert_eq!(
+        labels.validate(),
+        vec![
+            (Hash40(0x1234), String::from("custom")),
+            (Hash40(0x5678), String::from("typo")),
+        ]
+    );
+}
+
+#[test]
+#[cfg(feature = "embed-labels")]
+fn test_add_embedded_labels() {
+    let mut labels = LabelMap::default();
+    labels.add_embedded_labels();
+    let expected = EMBEDDED_LABELS
+        .lines()
+        .filter(|line| !LabelMap::is_skipped(line))
+        .collect::<std::collections::HashSet<_>>();
+    assert_eq!(labels.len(), expected.len());
+    for label in expected {
+        assert_eq!(labels.get_hash(label), Some(hash40(label)));
+    }
+}
+
+#[test]
+fn test_from_iterator() {
+    let labels = vec![String::from("fighter"), String::from("stage")]
+        .into_iter()
+        .collect::<LabelMap>();
+    assert_eq!(labels.get_hash("stage"), Some(hash40("stage")));
+    assert!(!labels.strict);
+
+    let custom = vec![(Hash40(0x1234), String::from("custom"))]
+        .into_iter()
+        .collect::<LabelMap>();
+    assert_eq!(custom.get_label(Hash40(0x1234)), Some("custom"));
+}
+
+#[test]
+fn test_longest_prefix_match() {
+    let labels = vec![String::from("fighter"), String::from("fighter/mario")]
+        .into_iter()
+        .collect::<LabelMap>();
+    assert_eq!(
+        labels.longest_prefix_match("fighter/mario/model.nutexb"),
+        Some(("fighter/mario", hash40("fighter/mario")))
+    );
+    assert_eq!(
+        labels.longest_prefix_match("fighter/luigi"),
+        Some(("fighter", hash40("fighter")))
+    );
+    assert_eq!(
+        labels.longest_prefix_match("fighter/mario"),
+        Some(("fighter/mario", hash40("fighter/mario")))
+    );
+    assert_eq!(labels.longest_prefix_match("fighters/mario"), None);
+}
+
+#[test]
+fn test_find_by_crc() {
+    let fighter = hash40("fighter");
+    let mut map = LabelMap::default();
+    map.add_labels(vec![String::from("fighter"), String::from("stage")]);
+    map.insert(
+        Hash40::from_parts(fighter.crc(), 3),
+        String::from("damaged"),
+    );
+
+    assert_eq!(
+        map.find_by_crc(fighter.crc()),
+        vec![
+            (
+                Hash40::from_parts(fighter.crc(), 3),
+                &String::from("damaged")
+            ),
+            (fighter, &String::from("fighter")),
+        ]
+    );
+    assert!(map.find_by_crc(0).is_empty());
+}
+
+#[test]
+fn test_crc_index() {
+    let fighter = hash40("fighter");
+    let damaged = Hash40::from_parts(fighter.crc(), 3);
+    let mut map = LabelMap::default().with_build_crc_index(true);
+    map.add_labels(vec![String::from("fighter"), String::from("stage")]);
+    map.insert(damaged, String::from("damaged"));
+    assert_eq!(map.find_by_crc(fighter.crc()).len(), 2);
+
+    map.remove(damaged);
+    assert_eq!(
+        map.find_by_crc(fighter.crc()),
+        vec![(fighter, &String::from("fighter"))]
+    );
+
+    // overwriting the label of a hash keeps a single index entry
+    map.insert(fighter, String::from("renamed"));
+    assert_eq!(
+        map.find_by_crc(fighter.crc()),
+        vec![(fighter, &String::from("renamed"))]
+    );
+
+    // moving a label to another hash removes the old hash from the index
+    map.insert(damaged, String::from("renamed"));
+    assert_eq!(
+        map.find_by_crc(fighter.crc()),
+        vec![(damaged, &String::from("renamed"))]
+    );
+
+    map.remove(damaged);
+    assert!(map.find_by_crc(fighter.crc()).is_empty());
+    assert!(!map.crc_index.contains_key(&fighter.crc()));
+}
+
+#[test]
+fn test_diff() {
+    let old = LabelMap::from_iter(vec![
+        (hash40("fighter"), String::from("fighter")),
+        (hash40("stage"), String::from("stage")),
+        (hash40("item"), String::from("item")),
+    ]);
+    // swapping two labels changes both hashes, which must not lose either label when applied
+    let new = LabelMap::from_iter(vec![
+        (hash40("stage"), String::from("item")),
+        (hash40("item"), String::from("stage")),
+        (hash40("param"), String::from("param")),
+    ]);
+
+    let diff = old.diff(&new);
+    assert_eq!(diff.added, vec![(hash40("param"), String::from("param"))]);
+    assert_eq!(diff.removed, vec![hash40("fighter")]);
+    assert_eq!(diff.changed.len(), 2);
+
+    let mut patched = old.clone();
+    patched.apply(&diff);
+    assert_eq!(patched.map, new.map);
+    assert_eq!(new.diff(&patched), LabelMapDiff::default());
+}
+
+#[test]
+fn test_custom_label_options() {
+    let label_first = CustomLabelOptions {
+        hash_column: 1,
+        label_column: 0,
+        ..Default::default()
+    };
+    let text = "fighter,0x077a08c3fc,comment\nstage,0x05c27c9369\nparam";
+    let result = LabelMap::read_custom_labels_from_with_options(text.as_bytes(), &label_first);
+    assert!(matches!(
+        result,
+        Err(CustomLabelError::MissingColumn { line: 3 })
+    ));
+    let labels = LabelMap::read_custom_labels_from_with_options(
+        &text.as_bytes()[..text.rfind('\n').unwrap()],
+        &label_first,
+    )
+    .unwrap();
+    assert_eq!(
+        labels,
+        vec![
+            (hash40("fighter"), String::from("fighter")),
+            (hash40("stage"), String::from("stage")),
+        ]
+    );
+
+    let tab_delimited = CustomLabelOptions {
+        hash_column: 1,
+        label_column: 2,
+        delimiter: '\t',
+    };
+    let text = "1\t0x077a08c3fc\tfighter\twith\ttabs";
+    assert_eq!(
+        LabelMap::read_custom_labels_from_with_options(text.as_bytes(), &tab_delimited).unwrap(),
+        vec![(hash40("fighter"), String::from("fighter\twith\ttabs"))]
+    );
+
+    let same_column = CustomLabelOptions {
+        hash_column: 1,
+        label_column: 1,
+        ..Default::default()
+    };
+    assert!(matches!(
+        LabelMap::read_custom_labels_from_with_options(&b"x,0x1234"[..], &same_column),
+        Err(CustomLabelError::SameColumn { column: 1 })
+    ));
+    assert!(matches!(
+        LabelMap::read_custom_labels_with_options("missing.csv", &same_column),
+        Err(CustomLabelError::SameColumn { column: 1 })
+    ));
+}
+
+#[test]
+fn test_skip_blank_lines_and_comments() {
+    let text = "# common labels\nfighter\n\n   \nstage\n";
+    assert_eq!(
+        LabelMap::read_labels_from(text.as_bytes()).unwrap(),
+        vec![String::from("fighter"), String::from("stage")]
+    );
+
+    let text = "# hash,label\n0x077a08c3fc,fighter\n\n0x05c27c9369,stage\nbad\n";
+    let result = LabelMap::read_custom_labels_from(text.as_bytes());
+    assert!(matches!(
+        result,
+        Err(CustomLabelError::MissingColumn { line: 5 })
+    ));
+    let labels = LabelMap::read_custom_labels_from(&text.as_bytes()[..text.len() - 4]).unwrap();
+    assert_eq!(labels.len(), 2);
+}
+
+#[test]
+fn test_try_hash_of() {
+    let mut map = LabelMap::from_iter(vec![String::from("fighter")]);
+    assert_eq!(map.try_hash_of("stage"), Ok(hash40("stage")));
+
+    map.strict = true;
+    assert_eq!(map.try_hash_of("fighter"), Ok(hash40("fighter")));
+    assert_eq!(
+        map.try_hash_of("stage"),
+        Err(FromLabelError::LabelNotFound(String::from("stage")))
+    );
+}
+
+#[test]
+fn test_strict_setters() {
+    let mut map = LabelMap::default().with_strict(true);
+    assert!(map.is_strict());
+    assert_eq!(map.hash_of("fighter"), None);
+
+    map.set_strict(false);
+    assert!(!map.is_strict());
+    assert_eq!(map.hash_of("fighter"), Some(hash40("fighter")));
+}
+
+#[test]
+#[cfg(feature = "ndjson")]
+fn test_ndjson() {
+    let text = r#"{"hash":"0x077a08c3fc","label":"fighter"}
+
+{"label":"custom, with a comma","hash":"0x1234"}
+"#;
+    let mut map = LabelMap::default();
+    map.add_labels_from_ndjson(text.as_bytes()).unwrap();
+    assert_eq!(map.get_label(hash40("fighter")), Some("fighter"));
+    assert_eq!(map.get_label(Hash40(0x1234)), Some("custom, with a comma"));
+
+    let malformed = "{\"hash\":\"0x1234\",\"label\":\"a\"}\n{\"hash\":\"0x5678\"}";
+    assert!(matches!(
+        LabelMap::default().add_labels_from_ndjson(malformed.as_bytes()),
+        Err(CustomLabelError::Json { line: 2, .. })
+    ));
+    assert!(matches!(
+        LabelMap::default().add_labels_from_ndjson(&br#"{"hash":"1234","label":"a"}"#[..]),
+        Err(CustomLabelError::ParseHashError {
+            line: 1,
+            error: ParseHashError::MissingPrefix
+        })
+    ));
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn test_serde() {
+    let mut map = LabelMap::from_iter(vec![(Hash40(0x1234), String::from("custom"))]);
+    map.add_labels(vec![String::from("fighter")]);
+    map.strict = true;
+
+    let json = serde_json::to_string(&map).unwrap();
+    assert_eq!(
+        json,
+        r#"{"strict":true,"labels":{"0x0000001234":"custom","0x077a08c3fc":"fighter"}}"#
+    );
+    let read = serde_json::from_str::<LabelMap>(&json).unwrap();
+    assert!(read.strict);
+    assert_eq!(read.map, map.map);
+
+    let read = serde_json::from_str::<LabelMap>(r#"{"labels":{}}"#).unwrap();
+    assert!(!read.strict);
+    assert!(read.is_initialized());
+
+    assert!(serde_json::from_str::<LabelMap>(r#"{"labels":{"0x1":"a","0x01":"b"}}"#).is_err());
+    assert!(serde_json::from_str::<LabelMap>(r#"{"labels":{"0x1":"a","0x2":"a"}}"#).is_err());
+    assert!(serde_json::from_str::<LabelMap>(r#"{"labels":{"fighter":"a"}}"#).is_err());
+}
+
+#[test]
+fn test_custom_label_error_into_io_error() {
+    let err = io::Error::from(CustomLabelError::MissingColumn { line: 2 });
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(err.to_string(), "missing label column on line 2");
+
+    let err = io::Error::from(CustomLabelError::Io(io::ErrorKind::NotFound.into()));
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+}
+
+#[test]
+fn test_labels_macro() {
+    let map = labels! {};
+    assert!(map.is_empty());
+
+    let map = labels! { 0x1234 => "custom", "fighter", "stage", };
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.get_label(hash40("fighter")), Some("fighter"));
+    assert_eq!(map.get_label(hash40("stage")), Some("stage"));
+    assert_eq!(map.get_label(Hash40(0x1234)), Some("custom"));
+}
+
+#[test]
+fn test_builder_from_env() {
+    let dir = std::env::temp_dir();
+    let labels_path = dir.join("hash40_test_env_labels.txt");
+    let custom_path = dir.join("hash40_test_env_labels.csv");
+    std::fs::write(&labels_path, "fighter\nstage\n").unwrap();
+    std::fs::write(&custom_path, "0x1234,custom\n").unwrap();
+
+    assert!(LabelMapBuilder::from_vars(|_| None).is_none());
+
+    fn vars(path: &Path, strict: Option<&'static str>) -> impl Fn(&str) -> Option<OsString> {
+        let path = path.as_os_str().to_owned();
+        move |name| match name {
+            LABEL_FILE_ENV => Some(path.clone()),
+            STRICT_ENV => strict.map(OsString::from),
+            _ => None,
+        }
     }
+
+    let map = LabelMapBuilder::from_vars(vars(&labels_path, None))
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.get_label(hash40("fighter")), Some("fighter"));
+    assert!(!map.is_strict());
+
+    let map = LabelMapBuilder::from_vars(vars(&custom_path, Some("TRUE")))
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(map.get_label(Hash40(0x1234)), Some("custom"));
+    assert!(map.is_strict());
+
+    let map = LabelMapBuilder::from_vars(vars(&custom_path, Some("0")))
+        .unwrap()
+        .build()
+        .unwrap();
+    assert!(!map.is_strict());
+
+    std::fs::remove_file(labels_path).unwrap();
+    std::fs::remove_file(custom_path).unwrap();
+}
+
+#[test]
+fn test_add_labels_reporting() {
+    let mut labels = labels! { "fighter" };
+    let report = labels.add_labels_reporting(vec![
+        String::from("stage"),
+        String::from("fighter"),
+        String::from("stage"),
+        String::from("FIGHTER"),
+    ]);
+    assert_eq!(
+        report,
+        LoadReport {
+            duplicates: vec![String::from("fighter"), String::from("stage")],
+            collisions: vec![(
+                hash40("fighter"),
+                String::from("fighter"),
+                String::from("FIGHTER")
+            )],
+            inserted: 2,
+            ..Default::default()
+        }
+    );
+    assert_eq!(labels.len(), 2);
+    assert_eq!(labels.get_label(hash40("fighter")), Some("FIGHTER"));
+
+    let mut labels = LabelMap::default();
+    labels.insert(Hash40(0x1234), String::from("fighter"));
+    labels.insert(hash40("stage"), String::from("STAGE"));
+    let report = labels.add_labels_reporting(vec![String::from("fighter"), String::from("stage")]);
+    assert_eq!(
+        report,
+        LoadReport {
+            collisions: vec![(
+                hash40("stage"),
+                String::from("STAGE"),
+                String::from("stage")
+            )],
+            remapped: vec![(Hash40(0x1234), String::from("fighter"))],
+            inserted: 2,
+            ..Default::default()
+        }
+    );
+    assert_eq!(labels.len(), 2);
+    assert_eq!(labels.get_label(hash40("fighter")), Some("fighter"));
+    assert_eq!(labels.get_label(Hash40(0x1234)), None);
+}
+
+#[test]
+fn test_crc_index_enabled_after_loading() {
+    let fighter = hash40("fighter");
+    let mut map = labels! { "fighter", "stage" };
+    assert!(!map.builds_crc_index());
+
+    map.set_build_crc_index(true);
+    assert!(map.builds_crc_index());
+    assert_eq!(
+        map.find_by_crc(fighter.crc()),
+        vec![(fighter, &String::from("fighter"))]
+    );
+
+    map.set_build_crc_index(false);
+    assert!(map.crc_index.is_empty());
+    assert_eq!(map.find_by_crc(fighter.crc()).len(), 1);
 }
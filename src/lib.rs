@@ -1,52 +1,281 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod algorithm;
+#[cfg(feature = "std")]
+pub mod binrw_5byte;
+#[cfg(feature = "std")]
+pub mod binrw_checked;
+pub mod bruteforce;
+#[cfg(feature = "std")]
+pub mod diffable;
 pub mod errors;
+#[cfg(feature = "std")]
+pub mod hash40_map;
+#[cfg(feature = "std")]
 pub mod label_map;
+pub mod prehashed;
+#[cfg(feature = "proptest")]
+pub mod proptest;
+#[cfg(feature = "serde")]
+pub mod serde_hex;
+#[cfg(feature = "serde")]
+pub mod serde_u64;
 
+#[cfg(feature = "std")]
 pub use binrw;
+#[cfg(feature = "std")]
 pub use diff;
 
-mod algorithm;
-
 use errors::*;
-use label_map::LabelMap;
+#[cfg(feature = "std")]
+use label_map::{CustomLabelError, LabelMap, LabelMapBuilder};
+use prehashed::PrehashedStr;
 
+#[cfg(feature = "std")]
 use binrw::binrw as binrw_attr;
+#[cfg(feature = "std")]
 use byteorder::{ByteOrder, ReadBytesExt, WriteBytesExt};
+#[cfg(feature = "std")]
 use diff::Diff;
+#[cfg(feature = "std")]
 use lazy_static::lazy_static;
 
-use std::fmt::{Display, Error as fmtError, Formatter};
+use alloc::format;
+use alloc::string::String;
+#[cfg(feature = "std")]
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use core::cmp::Ordering;
+use core::convert::TryFrom;
+use core::fmt::{self, Display, Error as fmtError, Formatter, LowerHex, UpperHex};
+#[cfg(feature = "std")]
+use core::marker::PhantomData;
+use core::num::{IntErrorKind, TryFromIntError};
+use core::ops::Deref;
+#[cfg(feature = "std")]
+use core::str::FromStr;
+#[cfg(feature = "std")]
+use std::cell::Cell;
+#[cfg(feature = "std")]
+use std::collections::HashSet;
+#[cfg(feature = "std")]
+use std::ffi::OsStr;
+#[cfg(feature = "std")]
 use std::io::{self, Read, Write};
-use std::ops::{Deref, DerefMut};
-use std::str::FromStr;
-use std::sync::{Arc, Mutex};
+#[cfg(feature = "std")]
+use std::path::{Component, Path};
+#[cfg(feature = "std")]
+use std::ptr;
+#[cfg(feature = "std")]
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 #[cfg(feature = "serde")]
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 
+#[cfg(feature = "std")]
 lazy_static! {
     /// The static map used for converting Hash40's between hash and string form.
-    static ref LABELS: Arc<Mutex<LabelMap>> = Arc::new(Mutex::new(LabelMap::default()));
+    static ref LABELS: Arc<RwLock<LabelMap>> = Arc::new(RwLock::new(LabelMap::default()));
+
+    /// Every label returned by [`Hash40::to_label_interned`], leaked so that it can be borrowed
+    /// for the rest of the program
+    static ref INTERNED_LABELS: RwLock<HashSet<&'static str>> = RwLock::new(HashSet::new());
 }
 
 /// The central type of the crate, representing a string hashed using the hash40 algorithm
 /// Hash40 is a combination of a crc32 checksum and string length appended to the top bits
-#[binrw_attr]
+#[cfg_attr(feature = "std", binrw_attr)]
 #[repr(transparent)]
 #[derive(Debug, Default, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Hash40(pub u64);
 
-/// An alias for Hash40::new, which creates a Hash40 from a string
+/// The CRC32 variant used to compute the checksum part of a hash. Smash Ultimate uses
+/// [`Crc32Variant::IsoHdlc`], which is the default and what [`Hash40::new`] always uses
+#[derive(Debug, Default, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum Crc32Variant {
+    /// CRC-32/ISO-HDLC, the reflected IEEE checksum used by zlib and Smash Ultimate
+    #[default]
+    IsoHdlc,
+    /// CRC-32/CKSUM, the non-reflected checksum with an initial value of 0
+    Cksum,
+}
+
+/// An alias for Hash40::new, which creates a Hash40 from a string.
+/// Because the hash algorithm is const, this can be used to define constant hashes
 pub const fn hash40(string: &str) -> Hash40 {
     Hash40::new(string)
 }
 
+/// Hashes a string at compile time, even outside of a const context. Strings longer than 255
+/// bytes, whose length can't be stored in the hash, fail to compile.
+///
+/// ```
+/// use hash40::{hash40, Hash40};
+///
+/// const FIGHTER: Hash40 = hash40!("fighter");
+/// assert_eq!(FIGHTER, hash40("fighter"));
+/// ```
+///
+/// ```compile_fail
+/// // 256 bytes
+/// let hash = hash40::hash40!(concat!(
+///     "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef",
+///     "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef",
+///     "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef",
+///     "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef",
+/// ));
+/// ```
+#[macro_export]
+macro_rules! hash40 {
+    ($string:expr) => {{
+        const HASH: $crate::Hash40 = match $crate::Hash40::new_checked($string) {
+            Some(hash) => hash,
+            None => panic!("hash40! strings must be at most 255 bytes long"),
+        };
+        HASH
+    }};
+}
+
+#[cfg(feature = "std")]
+thread_local! {
+    /// A label map which takes the place of the static map on the current thread.
+    /// Set by [`Hash40::with_label_map`]
+    static LABEL_OVERRIDE: Cell<*const LabelMap> = const { Cell::new(ptr::null()) };
+}
+
+#[cfg(feature = "std")]
+/// Runs the function with the current thread's label map override if there is one, or the
+/// static label map otherwise
+fn with_labels<R, F: FnOnce(&LabelMap) -> R>(f: F) -> R {
+    let current = LABEL_OVERRIDE.with(Cell::get);
+    if !current.is_null() {
+        // SAFETY: the pointer is only set within `Hash40::with_label_map`, which holds the
+        // borrow of the map until it restores the previous pointer
+        return f(unsafe { &*current });
+    }
+    f(&read_labels())
+}
+
+// Poisoned locks on the static label map are recovered from through `PoisonError::into_inner`,
+// rather than propagated to every later lookup. The lock can be poisoned by any code holding
+// it, including callers of `Hash40::label_map`, so the map may be left partway through an
+// update, but every update leaves it safe to read
+#[cfg(feature = "std")]
+fn read_labels() -> RwLockReadGuard<'static, LabelMap> {
+    LABELS.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(feature = "std")]
+/// Locks the static label map for writing. See [`read_labels`] for how poisoning is handled
+pub(crate) fn write_labels() -> RwLockWriteGuard<'static, LabelMap> {
+    LABELS.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(feature = "std")]
+/// Returns the interned copy of the label, leaking a new copy the first time it's seen
+fn intern(label: &str) -> &'static str {
+    let interned = INTERNED_LABELS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(label)
+        .copied();
+    if let Some(interned) = interned {
+        return interned;
+    }
+    let mut labels = INTERNED_LABELS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner);
+    if let Some(interned) = labels.get(label) {
+        return interned;
+    }
+    let leaked: &'static str = Box::leak(Box::from(label));
+    labels.insert(leaked);
+    leaked
+}
+
+#[cfg(feature = "std")]
+/// Sorts hashes alphabetically by their labels in the static label map, locking it only once.
+/// See [`Hash40::cmp_by_label`]
+pub fn sort_by_label(hashes: &mut [Hash40]) {
+    with_labels(|labels| hashes.sort_by(|a, b| labels.cmp_by_label(*a, *b)))
+}
+
+#[cfg(feature = "std")]
 /// An extension of the byteorder trait, to read a Hash40 from a stream
 pub trait ReadHash40: ReadBytesExt {
     fn read_hash40<T: ByteOrder>(&mut self) -> Result<Hash40, io::Error>;
 
     fn read_hash40_with_meta<T: ByteOrder>(&mut self) -> Result<(Hash40, u32), io::Error>;
+
+    /// Reads a hash stored in 8 bytes, followed by a separate 4 bytes of metadata. Unlike
+    /// [`Self::read_hash40_with_meta`], the metadata isn't packed into the top bits of the hash
+    fn read_hash40_with_u32_meta<T: ByteOrder>(&mut self) -> Result<(Hash40, u32), io::Error> {
+        Ok((self.read_hash40::<T>()?, self.read_u32::<T>()?))
+    }
+
+    /// Reads a hash stored in 5 bytes
+    fn read_hash40_5byte<T: ByteOrder>(&mut self) -> Result<Hash40, io::Error>;
+
+    /// Reads a hash stored in 5 bytes, followed by 1 byte of metadata
+    fn read_hash40_5byte_with_meta<T: ByteOrder>(&mut self) -> Result<(Hash40, u8), io::Error>;
+
+    /// Reads a hash, returning an `InvalidData` error if any bits above the lower 40 are set,
+    /// instead of masking them like [`Self::read_hash40`]. The 8 bytes are consumed either
+    /// way, so seeking back to try another interpretation is up to the caller
+    fn read_hash40_checked<T: ByteOrder>(&mut self) -> Result<Hash40, io::Error> {
+        Hash40::try_from(self.read_u64::<T>()?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// Reads a fixed number of consecutive hashes into an array
+    fn read_hash40_array<T: ByteOrder, const N: usize>(
+        &mut self,
+    ) -> Result<[Hash40; N], io::Error> {
+        let mut hashes = [Hash40::default(); N];
+        for hash in &mut hashes {
+            *hash = self.read_hash40::<T>()?;
+        }
+        Ok(hashes)
+    }
+
+    /// Reads `count` consecutive hashes. The vector grows as hashes are read, so a corrupt count
+    /// fails at the end of the stream instead of allocating up front
+    fn read_hash40_vec<T: ByteOrder>(&mut self, count: usize) -> Result<Vec<Hash40>, io::Error> {
+        (0..count).map(|_| self.read_hash40::<T>()).collect()
+    }
+
+    /// Returns an iterator which reads hashes until the end of the stream.
+    /// If the stream ends partway through a hash, an `UnexpectedEof` error is returned once
+    fn hash40_iter<T: ByteOrder>(&mut self) -> Hash40Iter<'_, Self, T>
+    where
+        Self: Sized,
+    {
+        Hash40Iter {
+            reader: self,
+            done: false,
+            order: PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+/// Converts 8 bytes, in the given byte order, to a Hash40 if no bits above the lower 40 are
+/// set. This is the check done by [`ReadHash40::read_hash40_checked`], without a stream
+pub fn validate_hash40_bytes<T: ByteOrder>(bytes: [u8; 8]) -> Result<Hash40, Hash40RangeError> {
+    Hash40::try_from(T::read_u64(&bytes))
+}
+
+#[cfg(feature = "std")]
+/// An iterator over the hashes of a stream, returned by [`ReadHash40::hash40_iter`]
+pub struct Hash40Iter<'a, R, T> {
+    reader: &'a mut R,
+    done: bool,
+    order: PhantomData<T>,
 }
 
+#[cfg(feature = "std")]
 /// An extension of the byteorder trait, to write a Hash40 into a stream
 pub trait WriteHash40: WriteBytesExt {
     fn write_hash40<T: ByteOrder>(&mut self, hash: Hash40) -> Result<(), io::Error>;
@@ -56,24 +285,104 @@ pub trait WriteHash40: WriteBytesExt {
         hash: Hash40,
         meta: u32,
     ) -> Result<(), io::Error>;
+
+    /// Writes a hash in 8 bytes, followed by a separate 4 bytes of metadata. See
+    /// [`ReadHash40::read_hash40_with_u32_meta`]
+    fn write_hash40_with_u32_meta<T: ByteOrder>(
+        &mut self,
+        hash: Hash40,
+        meta: u32,
+    ) -> Result<(), io::Error> {
+        self.write_hash40::<T>(hash)?;
+        self.write_u32::<T>(meta)
+    }
+
+    /// Writes a hash in 5 bytes
+    fn write_hash40_5byte<T: ByteOrder>(&mut self, hash: Hash40) -> Result<(), io::Error>;
+
+    /// Writes a hash in 5 bytes, followed by 1 byte of metadata
+    fn write_hash40_5byte_with_meta<T: ByteOrder>(
+        &mut self,
+        hash: Hash40,
+        meta: u8,
+    ) -> Result<(), io::Error>;
 }
 
 impl Hash40 {
+    /// A sentinel value which no string hashes to, since it has bits set above the lower 40.
+    /// Note that writing it to a stream masks it to 40 bits
+    pub const INVALID: Hash40 = Hash40(0xffff_ffff_ffff);
+
+    /// The hash of the empty string
+    pub const EMPTY: Hash40 = hash40("");
+
     /// Computes a Hash40 from a string. This method does not respect the static label map,
     /// nor does it check to see if the provided string is in hexadecimal format already.
+    ///
+    /// The length is stored in a single byte, so it wraps for strings longer than 255 bytes,
+    /// the same way the game computes it. See [`Self::new_checked`] to reject those strings
     pub const fn new(string: &str) -> Self {
-        Self(algorithm::hash40(string))
+        Self::hash_bytes(string.as_bytes())
     }
 
-    /// Converts a hexadecimal string representation of a hash to a Hash40
-    pub fn from_hex_str(value: &str) -> Result<Self, ParseHashError> {
-        if let Some(stripped) = value.strip_prefix("0x") {
-            Ok(Hash40(u64::from_str_radix(stripped, 16)?))
+    /// Computes a Hash40 from a string using the given CRC32 variant. Methods that combine
+    /// hashes, like [`Self::concat`], only work with hashes computed with the default variant
+    pub const fn new_with(string: &str, variant: Crc32Variant) -> Self {
+        match variant {
+            Crc32Variant::IsoHdlc => Self::new(string),
+            Crc32Variant::Cksum => Self(algorithm::hash40_cksum_bytes(string.as_bytes())),
+        }
+    }
+
+    /// Computes a Hash40 from raw bytes, which don't need to be valid UTF-8. Like
+    /// [`Self::new`], ASCII letters are hashed as lowercase
+    pub const fn hash_bytes(bytes: &[u8]) -> Self {
+        Self(algorithm::hash40_bytes(bytes))
+    }
+
+    #[cfg(feature = "crc32fast")]
+    /// Computes a Hash40 from raw bytes like [`Self::hash_bytes`], with a hardware-accelerated
+    /// CRC32 when available. This is faster for long inputs, but can't be used in const
+    /// contexts
+    pub fn hash_bytes_fast(bytes: &[u8]) -> Self {
+        Self(algorithm::hash40_bytes_fast(bytes))
+    }
+
+    /// Computes a Hash40 from a string like [`Self::new`], but returns `None` if the string is
+    /// longer than 255 bytes, and its length wouldn't fit in the hash
+    pub const fn new_checked(string: &str) -> Option<Self> {
+        if string.len() > u8::MAX as usize {
+            None
         } else {
-            Err(ParseHashError::MissingPrefix)
+            Some(Self::new(string))
+        }
+    }
+
+    /// Converts a hexadecimal string representation of a hash to a Hash40. Values which don't
+    /// fit in 40 bits are rejected, except for [`Self::INVALID`], so that it survives being
+    /// written with [`Self::to_hex_string`]
+    pub fn from_hex_str(value: &str) -> Result<Self, ParseHashError> {
+        let stripped = value
+            .strip_prefix("0x")
+            .ok_or(ParseHashError::MissingPrefix)?;
+        let hash = u64::from_str_radix(stripped, 16).map_err(|error| match error.kind() {
+            IntErrorKind::PosOverflow => ParseHashError::Overflow {
+                text: String::from(value),
+            },
+            _ => ParseHashError::ParseError {
+                text: String::from(value),
+                error,
+            },
+        })?;
+        if hash == Self::INVALID.0 {
+            return Ok(Self::INVALID);
         }
+        Self::try_from(hash).map_err(|_| ParseHashError::Overflow {
+            text: String::from(value),
+        })
     }
 
+    #[cfg(feature = "std")]
     /// Computes a Hash40 from a string. This method checks if the string is a hexadecimal
     /// value first. If not, it either searches for a reverse label from the static map or
     /// computes a new hash, depending on the form of the static label map.
@@ -81,32 +390,189 @@ impl Hash40 {
         match Self::from_hex_str(label) {
             Ok(hash) => Ok(hash),
             Err(err) => match err {
-                ParseHashError::MissingPrefix => {
-                    let lock = LABELS.lock();
-                    let labels = match lock {
-                        Ok(labels) => labels,
-                        Err(err) => err.into_inner(),
-                    };
-                    labels
-                        .hash_of(label)
-                        .ok_or_else(|| FromLabelError::LabelNotFound(String::from(label)))
+                ParseHashError::MissingPrefix => with_labels(|labels| labels.try_hash_of(label)),
+                ParseHashError::ParseError { text, error } => {
+                    Err(FromLabelError::ParseError { text, error })
                 }
-                ParseHashError::ParseError(err) => Err(err.into()),
+                ParseHashError::Overflow { text } => Err(FromLabelError::Overflow(text)),
             },
         }
     }
 
+    #[cfg(feature = "std")]
+    /// Parses a string that must be either a hexadecimal value or a label in the static map.
+    /// Unlike [`Self::from_label`], this never hashes an unknown label, regardless of the
+    /// map's `strict` flag, which makes it suitable for validating user input
+    pub fn parse_strict(label: &str) -> Result<Self, FromLabelError> {
+        match Self::from_hex_str(label) {
+            Ok(hash) => Ok(hash),
+            Err(ParseHashError::MissingPrefix) => with_labels(|labels| labels.get_hash(label))
+                .ok_or_else(|| FromLabelError::LabelNotFound(String::from(label))),
+            Err(ParseHashError::ParseError { text, error }) => {
+                Err(FromLabelError::ParseError { text, error })
+            }
+            Err(ParseHashError::Overflow { text }) => Err(FromLabelError::Overflow(text)),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    /// Computes a Hash40 from a string, without failing. Hexadecimal strings are parsed
+    /// directly, labels in the static map resolve to their hash, and anything else is hashed
+    /// with [`Self::new`]. Unlike [`Self::from_label`], this ignores the map's `strict` flag.
+    pub fn from_label_or_hash(label: &str) -> Self {
+        Self::from_hex_str(label).unwrap_or_else(|_| {
+            with_labels(|labels| labels.get_hash(label)).unwrap_or_else(|| hash40(label))
+        })
+    }
+
+    #[cfg(feature = "std")]
     /// Searches for the label associated with the hash value. If no label is found, returns
     /// the hexadecimal value, formatted as `0x0123456789`
     pub fn to_label(&self) -> String {
-        let lock = LABELS.lock();
-        let labels = match lock {
-            Ok(labels) => labels,
-            Err(err) => err.into_inner(),
-        };
+        self.to_label_or(|hash| hash.to_hex_string())
+    }
+
+    #[cfg(feature = "std")]
+    /// Searches for the label associated with the hash value, like [`Self::to_label`], but
+    /// calls `fallback` for the string to return if no label is found. The label map isn't
+    /// locked while `fallback` runs, so it may look up labels itself
+    pub fn to_label_or(&self, fallback: impl FnOnce(Hash40) -> String) -> String {
+        self.lookup_label(str::to_owned)
+            .unwrap_or_else(|| fallback(*self))
+    }
+
+    #[cfg(feature = "std")]
+    /// Runs `f` on the label of the hash in the current label map, if it has one. See
+    /// [`Self::lookup_label_in`]
+    fn lookup_label<R, F: FnOnce(&str) -> R>(self, f: F) -> Option<R> {
+        with_labels(|labels| self.lookup_label_in(labels).map(f))
+    }
+
+    #[cfg(feature = "std")]
+    /// Returns the label of the hash in `labels`. Nothing is found in a map which was never
+    /// initialized
+    fn lookup_label_in(self, labels: &LabelMap) -> Option<&str> {
         labels
-            .label_of(*self)
-            .unwrap_or_else(|| format!("0x{:010x}", self.0))
+            .is_initialized()
+            .then(|| labels.get_label(self))
+            .flatten()
+    }
+
+    /// Writes the label of the hash, or its hexadecimal value if no label is found, like
+    /// `to_label` but without allocating. Without the `std` feature, there is no label
+    /// map and the hexadecimal value is always written
+    pub fn write_label<W: fmt::Write>(&self, w: &mut W) -> fmt::Result {
+        #[cfg(feature = "std")]
+        if let Some(written) = self.lookup_label(|label| w.write_str(label)) {
+            return written;
+        }
+        write!(w, "0x{:010x}", self.0)
+    }
+
+    #[cfg(feature = "std")]
+    /// Returns the same string as [`Self::to_label`], but borrowed for the rest of the program.
+    /// Each distinct string is allocated once and never freed, so repeated calls don't
+    /// allocate. This is meant for long-running processes with a bounded set of labels
+    pub fn to_label_interned(&self) -> &'static str {
+        self.lookup_label(intern).unwrap_or_else(|| {
+            // the same string as `to_hex_string`, without allocating it
+            let digits = self.hex_digits();
+            let start = (self.0.leading_zeros() as usize / 4).min(6);
+            let mut hex = *b"0x0000000000000000";
+            let len = 2 + digits.len() - start;
+            hex[2..len].copy_from_slice(&digits[start..]);
+            intern(core::str::from_utf8(&hex[..len]).unwrap())
+        })
+    }
+
+    #[cfg(feature = "std")]
+    /// Returns the label of the hash only if the label map has one. For a path hash, this is
+    /// the full `/`-separated path. The components of a path hash can't be recovered from the
+    /// hash alone, so a path is only found if it was added to the map as a whole label
+    pub fn to_path_label(&self) -> Option<String> {
+        with_labels(|labels| labels.label_of(*self))
+    }
+
+    #[cfg(feature = "std")]
+    /// Searches for the labels of many hashes, locking the static label map only once.
+    /// Each result is the same as calling [`Self::to_label`] on the hash
+    pub fn to_labels<I: IntoIterator<Item = Hash40>>(hashes: I) -> Vec<String> {
+        with_labels(|labels| {
+            hashes
+                .into_iter()
+                .map(|hash| {
+                    hash.lookup_label_in(labels)
+                        .map_or_else(|| hash.to_hex_string(), str::to_owned)
+                })
+                .collect()
+        })
+    }
+
+    #[cfg(feature = "std")]
+    /// Compares two hashes by their labels in the static label map. Labelled hashes are
+    /// ordered before unlabelled ones, and two unlabelled hashes are compared numerically
+    pub fn cmp_by_label(&self, other: &Self) -> Ordering {
+        with_labels(|labels| labels.cmp_by_label(*self, *other))
+    }
+
+    #[cfg(feature = "std")]
+    /// Returns whether the static label map contains a label for the hash
+    pub fn is_labelled(&self) -> bool {
+        with_labels(|labels| labels.contains_hash(*self))
+    }
+
+    /// Returns the hexadecimal value of the hash, formatted as `0x0123456789`. Unlike
+    /// `to_label`, this method never consults the static label map. Any bits above the low 40,
+    /// like those of [`Self::INVALID`], are kept, so that the value isn't mistaken for a valid
+    /// hash
+    pub fn to_hex_string(&self) -> String {
+        format!("0x{:010x}", self.0)
+    }
+
+    /// Returns whether the candidate string has the length stored in the hash. This is a cheap
+    /// check to do before hashing the candidate
+    pub const fn matches_length(&self, candidate: &str) -> bool {
+        self.str_len() as usize == candidate.len()
+    }
+
+    /// Returns whether the candidate string hashes to this hash, checking the length first
+    pub const fn verify(&self, candidate: &str) -> bool {
+        self.matches_length(candidate) && hash40(candidate).0 == self.0
+    }
+
+    /// Creates a Hash40 from a CRC32 checksum and a string length, without rehashing
+    pub const fn from_parts(crc: u32, len: u8) -> Self {
+        Self((len as u64) << 32 | crc as u64)
+    }
+
+    /// Returns whether the hash is the [`Self::INVALID`] sentinel
+    pub const fn is_invalid(self) -> bool {
+        self.0 == Self::INVALID.0
+    }
+
+    /// Returns the 16 lowercase hex digits of the raw value
+    fn hex_digits(self) -> [u8; 16] {
+        let mut digits = [0; 16];
+        for (index, digit) in digits.iter_mut().enumerate() {
+            *digit = b"0123456789abcdef"[(self.0 >> (60 - 4 * index) & 0xf) as usize];
+        }
+        digits
+    }
+
+    /// Returns the raw value of the hash. Prefer this over dereferencing the hash, which exposes
+    /// every `u64` method
+    pub const fn as_u64(self) -> u64 {
+        self.0
+    }
+
+    /// Replaces the raw value of the hash, masking it to the low 40 bits
+    pub fn set_raw(&mut self, value: u64) {
+        self.0 = value & 0xff_ffff_ffff;
+    }
+
+    /// Returns whether the two hashes have the same CRC32, ignoring their lengths
+    pub const fn crc_eq(&self, other: &Self) -> bool {
+        self.crc() == other.crc()
     }
 
     /// Returns the CRC32 part of the hash
@@ -119,8 +585,103 @@ impl Hash40 {
         (self.0 >> 32) as u8
     }
 
+    /// Returns the 40-bit value of the hash as a 5-byte array, in little-endian order
+    pub const fn to_le_bytes(self) -> [u8; 5] {
+        let b = self.0.to_le_bytes();
+        [b[0], b[1], b[2], b[3], b[4]]
+    }
+
+    /// Returns the 40-bit value of the hash as a 5-byte array, in big-endian order
+    pub const fn to_be_bytes(self) -> [u8; 5] {
+        let b = self.0.to_be_bytes();
+        [b[3], b[4], b[5], b[6], b[7]]
+    }
+
+    /// Creates a Hash40 from its 5-byte, little-endian representation
+    pub const fn from_le_bytes(bytes: [u8; 5]) -> Self {
+        let [a, b, c, d, e] = bytes;
+        Self(u64::from_le_bytes([a, b, c, d, e, 0, 0, 0]))
+    }
+
+    /// Creates a Hash40 from its 5-byte, big-endian representation
+    pub const fn from_be_bytes(bytes: [u8; 5]) -> Self {
+        let [a, b, c, d, e] = bytes;
+        Self(u64::from_be_bytes([0, 0, 0, a, b, c, d, e]))
+    }
+
+    #[cfg(feature = "std")]
+    /// Returns whether labels have been loaded into the static label map yet
+    pub fn labels_loaded() -> bool {
+        with_labels(LabelMap::is_initialized)
+    }
+
+    #[cfg(feature = "std")]
+    /// Runs the function with `map` used in place of the static label map on the current
+    /// thread, for every method which reads labels. Other threads are unaffected, and the
+    /// static map is used again once the function returns
+    pub fn with_label_map<R, F: FnOnce() -> R>(map: &LabelMap, f: F) -> R {
+        struct Restore(*const LabelMap);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                LABEL_OVERRIDE.with(|current| current.set(self.0));
+            }
+        }
+
+        let _restore = Restore(LABEL_OVERRIDE.with(|current| current.replace(map)));
+        f()
+    }
+
+    #[cfg(feature = "std")]
+    /// Returns the number of labels in the static label map
+    pub fn labels_count() -> usize {
+        with_labels(LabelMap::len)
+    }
+
+    #[cfg(feature = "embed-labels")]
+    /// Inserts the labels embedded at build time into the static label map.
+    /// See [`LabelMap::add_embedded_labels`]
+    pub fn load_embedded_labels() {
+        write_labels().add_embedded_labels();
+    }
+
+    #[cfg(feature = "std")]
+    /// Returns a builder for loading label files, which can then be installed as the static
+    /// label map all at once with [`LabelMapBuilder::install`]
+    pub fn configure() -> LabelMapBuilder {
+        LabelMapBuilder::default()
+    }
+
+    #[cfg(feature = "std")]
+    /// Replaces the static label map with the label file named by the `HASH40_LABEL_FILE`
+    /// environment variable, which is read as custom labels if it has a `.csv` extension, and as
+    /// newline-separated labels otherwise. The map is strict if `HASH40_STRICT` is `1` or `true`.
+    ///
+    /// Returns `Ok(false)` without changing the static label map if `HASH40_LABEL_FILE` isn't
+    /// set. See [`LabelMapBuilder::from_env`]
+    pub fn load_labels_from_env() -> Result<bool, CustomLabelError> {
+        match LabelMapBuilder::from_env() {
+            Some(builder) => builder.install().map(|_| true),
+            None => Ok(false),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    /// Sets whether the static label map is strict. See [`LabelMap::hash_of`]
+    pub fn set_strict(strict: bool) {
+        write_labels().set_strict(strict);
+    }
+
+    #[cfg(feature = "std")]
+    /// Returns whether the current label map is strict. This is the static label map, unless
+    /// the thread is running within [`Self::with_label_map`]
+    pub fn is_strict() -> bool {
+        with_labels(LabelMap::is_strict)
+    }
+
+    #[cfg(feature = "std")]
     /// A convenience method provided to access the static label map
-    pub fn label_map() -> Arc<Mutex<LabelMap>> {
+    pub fn label_map() -> Arc<RwLock<LabelMap>> {
         LABELS.clone()
     }
 
@@ -135,12 +696,90 @@ impl Hash40 {
         self.concat(hash40(other))
     }
 
+    /// A convenience method for concatenating a string whose hash is already known to a
+    /// Hash40, without rehashing the string
+    pub const fn concat_prehashed(self, other: &PrehashedStr) -> Self {
+        self.concat(other.hash())
+    }
+
+    /// A convenience method for concatenating arbitrary bytes to a Hash40, which don't need to
+    /// be valid UTF-8
+    pub const fn concat_bytes(self, bytes: &[u8]) -> Self {
+        self.concat(Self::hash_bytes(bytes))
+    }
+
     /// A convenience method for concatenating two Hash40s separated by a path separator
     pub const fn join_path(self, other: Self) -> Self {
         self.concat_str("/").concat(other)
     }
+
+    #[cfg(feature = "std")]
+    /// Computes the hash of a path, with its components joined by `/` regardless of the
+    /// platform's separator. Root and prefix components (like `/` or `C:\`) and `.` components
+    /// are skipped, so `/fighter/./mario` hashes the same as `fighter/mario`.
+    ///
+    /// On Unix, components are hashed from their raw bytes, so non-UTF-8 paths keep distinct
+    /// hashes. On other platforms, non-UTF-8 components are converted lossily, replacing
+    /// invalid sequences with U+FFFD, so distinct paths may hash the same
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        Self::join_all(
+            path.as_ref()
+                .components()
+                .filter_map(|component| match component {
+                    Component::Normal(name) => Some(Self::hash_os_str(name)),
+                    Component::ParentDir => Some(hash40("..")),
+                    Component::RootDir | Component::Prefix(_) | Component::CurDir => None,
+                }),
+        )
+    }
+
+    #[cfg(feature = "std")]
+    /// Hashes a path component, see [`Self::from_path`]
+    fn hash_os_str(name: &OsStr) -> Self {
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            Self::hash_bytes(name.as_bytes())
+        }
+        #[cfg(not(unix))]
+        {
+            Self::new(&name.to_string_lossy())
+        }
+    }
+
+    /// Computes the hash of string segments joined by `/`, without building the joined string.
+    /// The result equals [`Self::new`] of the joined path, as long as each segment is at most
+    /// 255 bytes long. No segments returns the default hash
+    pub const fn from_segments(segments: &[&str]) -> Self {
+        if segments.is_empty() {
+            return Self(0);
+        }
+        let mut hash = Self::new(segments[0]);
+        let mut index = 1;
+        while index < segments.len() {
+            hash = hash.join_path(Self::new(segments[index]));
+            index += 1;
+        }
+        hash
+    }
+
+    /// Concatenates every Hash40 in the iterator, in order. An empty iterator returns the
+    /// default hash.
+    pub fn concat_all<I: IntoIterator<Item = Hash40>>(parts: I) -> Self {
+        parts.into_iter().reduce(Self::concat).unwrap_or_default()
+    }
+
+    /// Joins every Hash40 in the iterator, in order, separated by a path separator. An empty
+    /// iterator returns the default hash.
+    pub fn join_all<I: IntoIterator<Item = Hash40>>(parts: I) -> Self {
+        parts
+            .into_iter()
+            .reduce(Self::join_path)
+            .unwrap_or_default()
+    }
 }
 
+#[cfg(feature = "std")]
 impl FromStr for Hash40 {
     type Err = FromLabelError;
 
@@ -150,26 +789,120 @@ impl FromStr for Hash40 {
 }
 
 // Hash40 -> string
+/// Formats the hash as its label, falling back to hex. The alternate form (`{:#}`) always
+/// formats the hash as hex, without consulting the static label map
 impl Display for Hash40 {
     fn fmt(&self, f: &mut Formatter) -> Result<(), fmtError> {
-        write!(f, "{}", self.to_label())
+        if f.alternate() {
+            f.write_str(&self.to_hex_string())
+        } else {
+            self.write_label(f)
+        }
     }
 }
 
-impl Deref for Hash40 {
-    type Target = u64;
+/// Formats the 40-bit value of the hash as hex, always with at least 10 digits. Any width is
+/// applied around them, and the alternate form (`{:#x}`) prepends `0x`
+impl LowerHex for Hash40 {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmtError> {
+        let digits = Hash40(self.0 & 0xff_ffff_ffff).hex_digits();
+        let prefix = if f.alternate() { "0x" } else { "" };
+        f.pad_integral(true, prefix, core::str::from_utf8(&digits[6..]).unwrap())
+    }
+}
 
-    fn deref(&self) -> &Self::Target {
+/// Formats the 40-bit value of the hash as uppercase hex, like the [`LowerHex`] impl
+impl UpperHex for Hash40 {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmtError> {
+        let mut digits = Hash40(self.0 & 0xff_ffff_ffff).hex_digits();
+        digits.make_ascii_uppercase();
+        let prefix = if f.alternate() { "0x" } else { "" };
+        f.pad_integral(true, prefix, core::str::from_utf8(&digits[6..]).unwrap())
+    }
+}
+
+/// Compares the hash to the hash of the string, computed with [`Hash40::new`]. Unlike
+/// `Hash40::from_label`, this never consults the static label map.
+impl PartialEq<str> for Hash40 {
+    fn eq(&self, other: &str) -> bool {
+        *self == Hash40::new(other)
+    }
+}
+
+/// See the implementation of `PartialEq<str>`
+impl PartialEq<&str> for Hash40 {
+    fn eq(&self, other: &&str) -> bool {
+        *self == Hash40::new(other)
+    }
+}
+
+/// See the implementation of `PartialEq<str>`
+impl PartialEq<String> for Hash40 {
+    fn eq(&self, other: &String) -> bool {
+        *self == Hash40::new(other)
+    }
+}
+
+impl TryFrom<u64> for Hash40 {
+    type Error = Hash40RangeError;
+
+    fn try_from(value: u64) -> Result<Self, Hash40RangeError> {
+        if value & !0xff_ffff_ffff == 0 {
+            Ok(Hash40(value))
+        } else {
+            Err(Hash40RangeError(value))
+        }
+    }
+}
+
+/// Returns the raw value of the hash, the same as [`Hash40::as_u64`]. Hashes created by this
+/// crate fit in 40 bits, except for [`Hash40::INVALID`], which stays out of range
+impl From<Hash40> for u64 {
+    fn from(hash: Hash40) -> u64 {
+        hash.0
+    }
+}
+
+/// Returns the CRC32 of the hash, like [`Hash40::crc`], only if its length is zero and no
+/// higher bits are set, so that the conversion doesn't lose information
+impl TryFrom<Hash40> for u32 {
+    type Error = TryFromIntError;
+
+    fn try_from(hash: Hash40) -> Result<u32, TryFromIntError> {
+        u32::try_from(hash.0)
+    }
+}
+
+/// Creates a Hash40 from a `(crc, len)` pair. See [`Hash40::from_parts`]
+impl From<(u32, u8)> for Hash40 {
+    fn from((crc, len): (u32, u8)) -> Self {
+        Self::from_parts(crc, len)
+    }
+}
+
+impl AsRef<u64> for Hash40 {
+    fn as_ref(&self) -> &u64 {
         &self.0
     }
 }
 
-impl DerefMut for Hash40 {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+/// Kept for compatibility. New code should use [`Hash40::as_u64`] instead. The hash can't be
+/// mutated through it, so that it can't be given a value outside of the 40-bit range. Use
+/// [`Hash40::set_raw`] instead
+///
+/// ```compile_fail
+/// let mut hash = hash40::hash40("fighter");
+/// *hash = 0;
+/// ```
+impl Deref for Hash40 {
+    type Target = u64;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
     }
 }
 
+#[cfg(feature = "std")]
 impl<R: Read> ReadHash40 for R {
     fn read_hash40<T: ByteOrder>(&mut self) -> Result<Hash40, io::Error> {
         Ok(Hash40(self.read_u64::<T>()? & 0xff_ffff_ffff))
@@ -179,11 +912,57 @@ impl<R: Read> ReadHash40 for R {
         let long = self.read_u64::<T>()?;
         Ok((Hash40(long & 0xff_ffff_ffff), (long >> 40) as u32))
     }
+
+    fn read_hash40_5byte<T: ByteOrder>(&mut self) -> Result<Hash40, io::Error> {
+        Ok(Hash40(self.read_uint::<T>(5)?))
+    }
+
+    fn read_hash40_5byte_with_meta<T: ByteOrder>(&mut self) -> Result<(Hash40, u8), io::Error> {
+        Ok((self.read_hash40_5byte::<T>()?, self.read_u8()?))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a, R: Read, T: ByteOrder> Iterator for Hash40Iter<'a, R, T> {
+    type Item = Result<Hash40, io::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let mut bytes = [0u8; 8];
+        let mut filled = 0;
+        while filled < bytes.len() {
+            match self.reader.read(&mut bytes[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+
+        match filled {
+            0 => {
+                self.done = true;
+                None
+            }
+            8 => Some((&bytes[..]).read_hash40::<T>()),
+            _ => {
+                self.done = true;
+                Some(Err(io::Error::from(io::ErrorKind::UnexpectedEof)))
+            }
+        }
+    }
 }
 
+#[cfg(feature = "std")]
 impl<W: Write> WriteHash40 for W {
     fn write_hash40<T: ByteOrder>(&mut self, hash: Hash40) -> Result<(), io::Error> {
-        self.write_u64::<T>(hash.0)
+        self.write_u64::<T>(hash.0 & 0xff_ffff_ffff)
     }
 
     fn write_hash40_with_meta<T: ByteOrder>(
@@ -191,10 +970,24 @@ impl<W: Write> WriteHash40 for W {
         hash: Hash40,
         meta: u32,
     ) -> Result<(), io::Error> {
-        self.write_u64::<T>(hash.0 | (meta as u64) << 40)
+        self.write_u64::<T>(hash.0 & 0xff_ffff_ffff | (meta as u64) << 40)
+    }
+
+    fn write_hash40_5byte<T: ByteOrder>(&mut self, hash: Hash40) -> Result<(), io::Error> {
+        self.write_uint::<T>(hash.0 & 0xff_ffff_ffff, 5)
+    }
+
+    fn write_hash40_5byte_with_meta<T: ByteOrder>(
+        &mut self,
+        hash: Hash40,
+        meta: u8,
+    ) -> Result<(), io::Error> {
+        self.write_hash40_5byte::<T>(hash)?;
+        self.write_u8(meta)
     }
 }
 
+#[cfg(feature = "std")]
 impl Diff for Hash40 {
     type Repr = Option<Hash40>;
 
@@ -217,21 +1010,111 @@ impl Diff for Hash40 {
     }
 }
 
+/// Generates hashes within the 40-bit range, by masking an arbitrary `u64`
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Hash40 {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Hash40(u64::arbitrary(u)? & 0xff_ffff_ffff))
+    }
+
+    fn size_hint(depth: usize) -> (usize, Option<usize>) {
+        u64::size_hint(depth)
+    }
+}
+
+// SAFETY: Hash40 is a transparent wrapper around a u64, which is Zeroable
+#[cfg(feature = "bytemuck")]
+unsafe impl bytemuck::Zeroable for Hash40 {}
+
+/// Hash40 can be cast from any 8 bytes, so a cast hash can have bits set above the lower 40,
+/// which a hash read with [`ReadHash40::read_hash40`] never does. Cast hashes should be masked
+/// or checked with [`Hash40::try_from`] before they are trusted
+// SAFETY: Hash40 is a transparent wrapper around a u64, which is Pod
+#[cfg(feature = "bytemuck")]
+unsafe impl bytemuck::Pod for Hash40 {}
+
+/// Archives a Hash40 as a plain `u64`, so an archived table of hashes has the same layout as a
+/// table of archived integers
+#[cfg(feature = "rkyv")]
+impl rkyv::Archive for Hash40 {
+    type Archived = rkyv::primitive::ArchivedU64;
+    type Resolver = ();
+
+    fn resolve(&self, resolver: Self::Resolver, out: rkyv::Place<Self::Archived>) {
+        self.0.resolve(resolver, out)
+    }
+}
+
+#[cfg(feature = "rkyv")]
+impl<S: rkyv::rancor::Fallible + ?Sized> rkyv::Serialize<S> for Hash40 {
+    fn serialize(&self, _: &mut S) -> Result<Self::Resolver, S::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "rkyv")]
+impl<D: rkyv::rancor::Fallible + ?Sized> rkyv::Deserialize<Hash40, D>
+    for rkyv::primitive::ArchivedU64
+{
+    fn deserialize(&self, _: &mut D) -> Result<Hash40, D::Error> {
+        Ok(Hash40(self.to_native()))
+    }
+}
+
 #[cfg(feature = "serde")]
 impl<'de> de::Visitor<'de> for Hash40Visitor {
     type Value = Hash40;
 
     fn expecting(
         &self,
-        formatter: &mut std::fmt::Formatter,
-    ) -> std::result::Result<(), std::fmt::Error> {
+        formatter: &mut core::fmt::Formatter,
+    ) -> core::result::Result<(), core::fmt::Error> {
         formatter.write_str(
-            "A hex-formatted integer hash value, or a string representing for its reversed form",
+            "An integer hash value, a hex-formatted integer hash value, a string representing \
+            for its reversed form, or a map of its crc and length",
         )
     }
 
     fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
-        Hash40::from_label(&String::from(value)).map_err(de::Error::custom)
+        Hash40::from_label(value).map_err(de::Error::custom)
+    }
+
+    fn visit_u32<E: de::Error>(self, value: u32) -> Result<Self::Value, E> {
+        Ok(Hash40(value as u64))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+        Ok(Hash40(value & 0xff_ffff_ffff))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+        if value < 0 {
+            return Err(de::Error::invalid_value(
+                de::Unexpected::Signed(value),
+                &self,
+            ));
+        }
+        self.visit_u64(value as u64)
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut crc = None;
+        let mut length = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "crc" if crc.is_none() => crc = Some(map.next_value::<u32>()?),
+                "length" if length.is_none() => length = Some(map.next_value::<u64>()?),
+                "crc" => return Err(de::Error::duplicate_field("crc")),
+                "length" => return Err(de::Error::duplicate_field("length")),
+                _ => return Err(de::Error::unknown_field(&key, &["crc", "length"])),
+            }
+        }
+        let crc = crc.ok_or_else(|| de::Error::missing_field("crc"))?;
+        let length = length.ok_or_else(|| de::Error::missing_field("length"))?;
+        let length = u8::try_from(length).map_err(|_| {
+            de::Error::invalid_value(de::Unexpected::Unsigned(length), &"a length up to 255")
+        })?;
+        Ok(Hash40::from_parts(crc, length))
     }
 }
 
@@ -244,11 +1127,739 @@ impl Serialize for Hash40 {
 
 #[cfg(feature = "serde")]
 impl<'de> Deserialize<'de> for Hash40 {
+    /// In human-readable formats, deserializes either a string (see [`Hash40::from_label`]), an
+    /// integer, which is masked to 40 bits, or a map of its `crc` and `length` (see
+    /// [`Hash40::from_parts`]). Other formats only read the string written by serialization. To
+    /// store the raw integer instead, see [`serde_u64`]
     fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
-        deserializer.deserialize_str(Hash40Visitor)
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(Hash40Visitor)
+        } else {
+            deserializer.deserialize_str(Hash40Visitor)
+        }
     }
 }
 
 #[cfg(feature = "serde")]
 /// Used to implement serde's Deserialize trait
 struct Hash40Visitor;
+
+#[test]
+fn test_try_from_u64() {
+    assert_eq!(Hash40::try_from(0x00_0000_0000), Ok(Hash40(0)));
+    assert_eq!(Hash40::try_from(0xff_ffff_ffff), Ok(Hash40(0xff_ffff_ffff)));
+    assert_eq!(
+        Hash40::try_from(0x0100_0000_0000),
+        Err(Hash40RangeError(0x0100_0000_0000))
+    );
+}
+
+#[test]
+fn test_to_hex_string() {
+    assert_eq!(Hash40(0).to_hex_string(), "0x0000000000");
+    assert_eq!(hash40("damage_max").to_hex_string(), "0x0aa3cb8810");
+    assert_eq!(
+        Hash40::from_hex_str(&hash40("fighter").to_hex_string()),
+        Ok(hash40("fighter"))
+    );
+}
+
+#[test]
+fn test_5_byte_conversion() {
+    assert_eq!(hash40("a").to_le_bytes(), [0x43, 0xbe, 0xb7, 0xe8, 0x01]);
+    assert_eq!(hash40("a").to_be_bytes(), [0x01, 0xe8, 0xb7, 0xbe, 0x43]);
+
+    for hash in [
+        Hash40(0),
+        Hash40(0xff_ffff_ffff),
+        hash40("a"),
+        hash40("damage_max"),
+        hash40("fighter"),
+    ] {
+        assert_eq!(Hash40::from_le_bytes(hash.to_le_bytes()), hash);
+        assert_eq!(Hash40::from_be_bytes(hash.to_be_bytes()), hash);
+    }
+}
+
+#[test]
+fn test_concat_all() {
+    assert_eq!(Hash40::concat_all([]), Hash40::default());
+    assert_eq!(Hash40::join_all([]), Hash40::default());
+    assert_eq!(
+        Hash40::concat_all([hash40("a"), hash40("b"), hash40("c")]),
+        hash40("abc")
+    );
+    assert_eq!(
+        Hash40::join_all([hash40("fighter"), hash40("mario"), hash40("model")]),
+        hash40("fighter/mario/model")
+    );
+}
+
+#[test]
+fn test_from_parts() {
+    for hash in [
+        Hash40(0),
+        Hash40(0xff_ffff_ffff),
+        hash40("a"),
+        hash40("damage_max"),
+        hash40("fighter/mario/model"),
+    ] {
+        assert_eq!(Hash40::from_parts(hash.crc(), hash.str_len()), hash);
+    }
+}
+
+#[test]
+fn test_const_hash() {
+    const FIGHTER: Hash40 = hash40("fighter");
+    const DAMAGE_MAX: Hash40 = hash40("damage_max");
+    const JOINED: Hash40 = FIGHTER.join_path(DAMAGE_MAX);
+    assert_eq!(FIGHTER, Hash40::new("fighter"));
+    assert_eq!(DAMAGE_MAX, Hash40(0x0aa3cb8810));
+    assert_eq!(JOINED, hash40("fighter/damage_max"));
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_from_label_or_hash() {
+    assert_eq!(Hash40::from_label_or_hash("0x1234"), Hash40(0x1234));
+    assert_eq!(Hash40::from_label_or_hash("fighter"), hash40("fighter"));
+    assert_eq!(Hash40::from_label_or_hash("0xzz"), hash40("0xzz"));
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn test_deserialize_str_or_int() {
+    assert_eq!(
+        serde_json::from_str::<Hash40>(r#""fighter""#).unwrap(),
+        hash40("fighter")
+    );
+    assert_eq!(
+        serde_json::from_str::<Hash40>(r#""0x077a08c3fc""#).unwrap(),
+        hash40("fighter")
+    );
+    assert_eq!(
+        serde_json::from_str::<Hash40>("32112165884").unwrap(),
+        hash40("fighter")
+    );
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn test_deserialize_parts() {
+    assert_eq!(
+        serde_json::from_str::<Hash40>(r#"{"crc": 2047394812, "length": 7}"#).unwrap(),
+        hash40("fighter")
+    );
+    assert!(serde_json::from_str::<Hash40>(r#"{"crc": 1, "length": 256}"#).is_err());
+    assert!(serde_json::from_str::<Hash40>(r#"{"crc": 1}"#).is_err());
+    assert!(serde_json::from_str::<Hash40>(r#"{"crc": 1, "length": 1, "x": 0}"#).is_err());
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_5byte_read_write() {
+    use byteorder::{BigEndian, LittleEndian};
+
+    let mut buffer = Vec::new();
+    buffer
+        .write_hash40_5byte::<LittleEndian>(hash40("a"))
+        .unwrap();
+    buffer
+        .write_hash40_5byte_with_meta::<BigEndian>(hash40("a"), 0x12)
+        .unwrap();
+    assert_eq!(
+        buffer,
+        [0x43, 0xbe, 0xb7, 0xe8, 0x01, 0x01, 0xe8, 0xb7, 0xbe, 0x43, 0x12]
+    );
+
+    let mut reader = &buffer[..];
+    assert_eq!(
+        reader.read_hash40_5byte::<LittleEndian>().unwrap(),
+        hash40("a")
+    );
+    assert_eq!(
+        reader.read_hash40_5byte_with_meta::<BigEndian>().unwrap(),
+        (hash40("a"), 0x12)
+    );
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_write_hash40_masks() {
+    use byteorder::LittleEndian;
+
+    let mut buffer = Vec::new();
+    buffer
+        .write_hash40::<LittleEndian>(Hash40(u64::MAX))
+        .unwrap();
+    buffer
+        .write_hash40_with_meta::<LittleEndian>(Hash40(u64::MAX), 0x123456)
+        .unwrap();
+    assert_eq!(
+        buffer,
+        [
+            0xff, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, //
+            0xff, 0xff, 0xff, 0xff, 0xff, 0x56, 0x34, 0x12,
+        ]
+    );
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_hash40_iter() {
+    use byteorder::LittleEndian;
+
+    let mut buffer = Vec::new();
+    buffer.write_hash40::<LittleEndian>(hash40("a")).unwrap();
+    buffer.write_hash40::<LittleEndian>(hash40("b")).unwrap();
+
+    let hashes = (&buffer[..])
+        .hash40_iter::<LittleEndian>()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(hashes, vec![hash40("a"), hash40("b")]);
+
+    buffer.extend_from_slice(&[0, 0, 0]);
+    let mut reader = &buffer[..];
+    let mut iter = reader.hash40_iter::<LittleEndian>();
+    assert_eq!(iter.next().unwrap().unwrap(), hash40("a"));
+    assert_eq!(iter.next().unwrap().unwrap(), hash40("b"));
+    assert_eq!(
+        iter.next().unwrap().unwrap_err().kind(),
+        io::ErrorKind::UnexpectedEof
+    );
+    assert!(iter.next().is_none());
+}
+
+#[test]
+fn test_constants() {
+    assert_eq!(Hash40::EMPTY, Hash40(0));
+    assert!(Hash40::INVALID.is_invalid());
+    assert!(!Hash40::EMPTY.is_invalid());
+    assert!(Hash40::try_from(Hash40::INVALID.0).is_err());
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_with_label_map() {
+    let outer = labels! { "fighter" };
+    let mut inner = LabelMap::default();
+    inner.strict = true;
+    inner.add_custom_labels(vec![(hash40("fighter"), String::from("inner"))].into_iter());
+
+    Hash40::with_label_map(&outer, || {
+        assert_eq!(hash40("fighter").to_label(), "fighter");
+        assert_eq!(
+            hash40("fighter").to_path_label(),
+            Some(String::from("fighter"))
+        );
+        assert_eq!(hash40("stage").to_path_label(), None);
+        assert!(Hash40::from_label("stage").is_ok());
+
+        Hash40::with_label_map(&inner, || {
+            assert_eq!(hash40("fighter").to_label(), "inner");
+            assert_eq!(Hash40::from_label("inner"), Ok(hash40("fighter")));
+            assert!(Hash40::from_label("stage").is_err());
+            std::thread::spawn(|| assert!(!hash40("fighter").is_labelled()))
+                .join()
+                .unwrap();
+        });
+
+        assert_eq!(hash40("fighter").to_label(), "fighter");
+    });
+}
+
+#[test]
+fn test_verify() {
+    let fighter = hash40("fighter");
+    assert!(fighter.matches_length("abcdefg"));
+    assert!(!fighter.matches_length("abc"));
+    assert!(fighter.verify("fighter"));
+    assert!(fighter.verify("FIGHTER"));
+    assert!(!fighter.verify("abcdefg"));
+}
+
+#[test]
+fn test_eq_str() {
+    assert_eq!(hash40("fighter"), "fighter");
+    assert_eq!(hash40("fighter"), *"FIGHTER");
+    assert_eq!(hash40("fighter"), String::from("fighter"));
+    assert_ne!(hash40("fighter"), "stage");
+    assert_ne!(Hash40(0x1234), "0x1234");
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_from_path() {
+    let expected = hash40("fighter/mario/model.nutexb");
+    assert_eq!(Hash40::from_path("fighter/mario/model.nutexb"), expected);
+    assert_eq!(Hash40::from_path("/fighter/./mario/model.nutexb"), expected);
+    assert_eq!(
+        Hash40::from_path(Path::new("fighter").join("mario").join("model.nutexb")),
+        expected
+    );
+    assert_eq!(
+        Hash40::from_path("fighter/../stage"),
+        hash40("fighter/../stage")
+    );
+    assert_eq!(Hash40::from_path(""), Hash40::default());
+}
+
+#[test]
+fn test_concat_bytes() {
+    assert_eq!(hash40("ab").concat_str("cd"), hash40("abcd"));
+    assert_eq!(hash40("ab").concat_bytes(b"cd"), hash40("abcd"));
+    assert_eq!(
+        hash40("ab").concat_bytes(&[0xff, 0x00]),
+        Hash40::default().concat_bytes(&[b'a', b'b', 0xff, 0x00])
+    );
+    assert_eq!(hash40("ab").concat_bytes(&[]), hash40("ab"));
+}
+
+#[test]
+fn test_new_checked() {
+    let max = "a".repeat(255);
+    let overlong = "a".repeat(256);
+    assert_eq!(Hash40::new_checked(&max), Some(Hash40::new(&max)));
+    assert_eq!(Hash40::new(&max).str_len(), 255);
+    assert_eq!(Hash40::new_checked(&overlong), None);
+    assert_eq!(Hash40::new(&overlong).str_len(), 0);
+}
+
+#[test]
+fn test_hash_bytes() {
+    assert_eq!(Hash40::hash_bytes(b"fighter"), hash40("fighter"));
+    assert_eq!(Hash40::hash_bytes(&[0xff]), Hash40(0x01ff000000));
+    assert_eq!(Hash40::hash_bytes(&[0xff]).str_len(), 1);
+}
+
+#[test]
+fn test_as_u64() {
+    let hash = hash40("fighter");
+    assert_eq!(hash.as_u64(), 0x077a08c3fc);
+    assert_eq!(*hash.as_ref(), hash.as_u64());
+}
+
+#[test]
+fn test_set_raw() {
+    let mut hash = Hash40::EMPTY;
+    hash.set_raw(0xffff_ff07_7a08_c3fc);
+    assert_eq!(hash, hash40("fighter"));
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_parse_strict() {
+    let labels = labels! { "fighter" };
+
+    Hash40::with_label_map(&labels, || {
+        assert_eq!(Hash40::parse_strict("fighter"), Ok(hash40("fighter")));
+        assert_eq!(Hash40::parse_strict("0x077a08c3fc"), Ok(hash40("fighter")));
+        assert_eq!(
+            Hash40::parse_strict("stage"),
+            Err(FromLabelError::LabelNotFound(String::from("stage")))
+        );
+        assert!(matches!(
+            Hash40::parse_strict("0xZZZ"),
+            Err(FromLabelError::ParseError { text, .. }) if text == "0xZZZ"
+        ));
+        assert!(Hash40::from_label("stage").is_ok());
+    });
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_display_alternate() {
+    let labels = labels! { "fighter" };
+
+    Hash40::with_label_map(&labels, || {
+        assert_eq!(format!("{}", hash40("fighter")), "fighter");
+        assert_eq!(format!("{:#}", hash40("fighter")), "0x077a08c3fc");
+    });
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_hex_format() {
+    let hash = hash40("fighter");
+    assert_eq!(format!("{:x}", hash), "077a08c3fc");
+    assert_eq!(format!("{:#x}", hash), "0x077a08c3fc");
+    assert_eq!(format!("{:010x}", hash), "077a08c3fc");
+    assert_eq!(format!("{:012x}", hash), "00077a08c3fc");
+    assert_eq!(format!("{:>14x}", hash), "    077a08c3fc");
+    assert_eq!(format!("{:<12x}|", hash), "077a08c3fc  |");
+    assert_eq!(format!("{:#014x}", hash), "0x00077a08c3fc");
+    assert_eq!(format!("{:4x}", hash), "077a08c3fc");
+    assert_eq!(format!("{:x}", Hash40(0xffff_ff07_7a08_c3fc)), "077a08c3fc");
+    assert_eq!(format!("{:X}", hash), "077A08C3FC");
+    assert_eq!(format!("{:#X}", Hash40::EMPTY), "0x0000000000");
+}
+
+#[test]
+fn test_new_with() {
+    assert_eq!(
+        Hash40::new_with("fighter", Crc32Variant::default()),
+        hash40("fighter")
+    );
+    assert_eq!(
+        Hash40::new_with("123456789", Crc32Variant::Cksum),
+        Hash40(0x09765e7680)
+    );
+}
+
+#[test]
+fn test_crc_eq() {
+    let fighter = hash40("fighter");
+    assert!(fighter.crc_eq(&Hash40::from_parts(fighter.crc(), 0)));
+    assert!(!fighter.crc_eq(&hash40("stage")));
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_to_label_interned() {
+    let labels = labels! { "fighter" };
+
+    Hash40::with_label_map(&labels, || {
+        let first = hash40("fighter").to_label_interned();
+        assert_eq!(first, "fighter");
+        assert!(core::ptr::eq(first, hash40("fighter").to_label_interned()));
+        assert_eq!(
+            hash40("stage").to_label_interned(),
+            hash40("stage").to_label()
+        );
+    });
+    assert_eq!(hash40("fighter").to_label_interned(), "0x077a08c3fc");
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_write_label() {
+    let labels = labels! { "fighter" };
+
+    let mut out = String::new();
+    Hash40::with_label_map(&labels, || {
+        hash40("fighter").write_label(&mut out).unwrap();
+        out.push(',');
+        hash40("stage").write_label(&mut out).unwrap();
+    });
+    assert_eq!(out, "fighter,0x05c27c9369");
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_hex_parse_error_text() {
+    let err = Hash40::from_hex_str("0xZZZ").unwrap_err();
+    assert!(matches!(&err, ParseHashError::ParseError { text, .. } if text == "0xZZZ"));
+    assert_eq!(
+        err.to_string(),
+        "failed to parse hex hash \"0xZZZ\": invalid digit found in string"
+    );
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_hex_overflow() {
+    assert_eq!(
+        Hash40::from_hex_str("0xffffffffff"),
+        Ok(Hash40(0xff_ffff_ffff))
+    );
+    assert_eq!(
+        Hash40::from_hex_str("0x000077a08c3fc"),
+        Ok(hash40("fighter"))
+    );
+    assert_eq!(
+        Hash40::from_hex_str("0x10000000000"),
+        Err(ParseHashError::Overflow {
+            text: String::from("0x10000000000")
+        })
+    );
+    assert_eq!(
+        Hash40::from_label("0xffffffffffffffff"),
+        Err(FromLabelError::Overflow(String::from("0xffffffffffffffff")))
+    );
+    assert_eq!(
+        Hash40::from_hex_str("0x1ffffffffffffffff"),
+        Err(ParseHashError::Overflow {
+            text: String::from("0x1ffffffffffffffff")
+        })
+    );
+    assert_eq!(
+        Hash40::from_label("0x1ffffffffffffffff"),
+        Err(FromLabelError::Overflow(String::from(
+            "0x1ffffffffffffffff"
+        )))
+    );
+    // leading zeros don't overflow
+    assert_eq!(
+        Hash40::from_hex_str("0x00000000000077a08c3fc"),
+        Ok(hash40("fighter"))
+    );
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_is_strict() {
+    // the static map is never strict during tests
+    Hash40::set_strict(false);
+    assert!(!Hash40::is_strict());
+
+    let labels = LabelMap::default().with_strict(true);
+    Hash40::with_label_map(&labels, || assert!(Hash40::is_strict()));
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_poisoned_label_map() {
+    // poisoning the static map doesn't change it, so this can't affect other tests
+    let _ = std::thread::spawn(|| {
+        let _labels = write_labels();
+        panic!("poisoning the static label map");
+    })
+    .join();
+    assert!(LABELS.is_poisoned());
+    assert!(!hash40("fighter").is_labelled());
+    assert!(!write_labels().is_strict());
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_read_hash40_array() {
+    use byteorder::BigEndian;
+
+    let mut buffer = Vec::new();
+    for label in ["a", "b", "c"] {
+        buffer.write_hash40::<BigEndian>(hash40(label)).unwrap();
+    }
+
+    let mut reader = &buffer[..];
+    assert_eq!(
+        reader.read_hash40_array::<BigEndian, 3>().unwrap(),
+        [hash40("a"), hash40("b"), hash40("c")]
+    );
+    assert!(reader.is_empty());
+
+    let mut reader = &buffer[..];
+    assert_eq!(
+        reader.read_hash40_vec::<BigEndian>(2).unwrap(),
+        vec![hash40("a"), hash40("b")]
+    );
+    assert_eq!(
+        (&buffer[..])
+            .read_hash40_vec::<BigEndian>(4)
+            .unwrap_err()
+            .kind(),
+        io::ErrorKind::UnexpectedEof
+    );
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_read_hash40_checked() {
+    use byteorder::{BigEndian, LittleEndian};
+
+    let bytes = 0x077a08c3fc_u64.to_le_bytes();
+    assert_eq!(
+        validate_hash40_bytes::<LittleEndian>(bytes),
+        Ok(hash40("fighter"))
+    );
+    assert_eq!(
+        validate_hash40_bytes::<BigEndian>(bytes),
+        Err(Hash40RangeError(0xfcc3_087a_0700_0000))
+    );
+
+    let mut reader = &bytes[..];
+    assert_eq!(
+        reader.read_hash40_checked::<LittleEndian>().unwrap(),
+        hash40("fighter")
+    );
+    let mut reader = &bytes[..];
+    let err = reader.read_hash40_checked::<BigEndian>().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(reader.is_empty());
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_u32_meta_read_write() {
+    use byteorder::LittleEndian;
+
+    let mut buffer = Vec::new();
+    buffer
+        .write_hash40_with_u32_meta::<LittleEndian>(hash40("a"), 0x12345678)
+        .unwrap();
+    assert_eq!(
+        buffer,
+        [0x43, 0xbe, 0xb7, 0xe8, 0x01, 0, 0, 0, 0x78, 0x56, 0x34, 0x12]
+    );
+    assert_eq!(
+        (&buffer[..])
+            .read_hash40_with_u32_meta::<LittleEndian>()
+            .unwrap(),
+        (hash40("a"), 0x12345678)
+    );
+}
+
+#[test]
+fn test_from_segments() {
+    assert_eq!(
+        Hash40::from_segments(&["fighter", "mario", "model.nutexb"]),
+        hash40("fighter/mario/model.nutexb")
+    );
+    assert_eq!(Hash40::from_segments(&["fighter"]), hash40("fighter"));
+    assert_eq!(Hash40::from_segments(&[]), Hash40::default());
+
+    const PATH: Hash40 = Hash40::from_segments(&["fighter", "mario"]);
+    assert_eq!(PATH, hash40("fighter/mario"));
+}
+
+#[test]
+#[cfg(all(feature = "rkyv", feature = "std"))]
+fn test_rkyv() {
+    use rkyv::rancor::Error;
+
+    let hashes = vec![hash40("fighter"), hash40("stage")];
+    let bytes = rkyv::to_bytes::<Error>(&hashes).unwrap();
+    let raw = rkyv::to_bytes::<Error>(&vec![hash40("fighter").0, hash40("stage").0]).unwrap();
+    assert_eq!(&bytes[..], &raw[..]);
+
+    let archived = rkyv::access::<rkyv::Archived<Vec<Hash40>>, Error>(&bytes).unwrap();
+    assert_eq!(archived[0].to_native(), hash40("fighter").0);
+    assert_eq!(
+        rkyv::deserialize::<Vec<Hash40>, Error>(archived).unwrap(),
+        hashes
+    );
+
+    let single = rkyv::to_bytes::<Error>(&hash40("fighter")).unwrap();
+    assert_eq!(&single[..], &0x077a08c3fc_u64.to_le_bytes());
+}
+
+#[test]
+#[cfg(feature = "bytemuck")]
+fn test_bytemuck() {
+    let raw = alloc::vec![hash40("fighter").0, 0xffff_ffff_ffff_ffff];
+    let hashes: &[Hash40] = bytemuck::cast_slice(&raw);
+    assert_eq!(hashes[0], hash40("fighter"));
+    assert!(Hash40::try_from(hashes[1].as_u64()).is_err());
+    let zeroed: Hash40 = bytemuck::Zeroable::zeroed();
+    assert_eq!(zeroed, Hash40::default());
+}
+
+#[test]
+#[cfg(feature = "arbitrary")]
+fn test_arbitrary() {
+    use arbitrary::{Arbitrary, Unstructured};
+
+    let mut u = Unstructured::new(&[0xff; 8]);
+    assert_eq!(Hash40::arbitrary(&mut u).unwrap(), Hash40(0xff_ffff_ffff));
+
+    let mut bytes = [0u8; 256];
+    for (index, byte) in bytes.iter_mut().enumerate() {
+        *byte = (index as u8).wrapping_mul(151).wrapping_add(7);
+    }
+    let mut u = Unstructured::new(&bytes);
+    for _ in 0..32 {
+        let hash = Hash40::arbitrary(&mut u).unwrap();
+        assert!(Hash40::try_from(hash.as_u64()).is_ok());
+    }
+}
+
+#[test]
+fn test_hash40_macro() {
+    assert_eq!(hash40!("fighter"), hash40("fighter"));
+    assert_eq!(
+        hash40!(concat!("fighter", "/", "mario")),
+        hash40("fighter/mario")
+    );
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn test_to_label_or() {
+    let labels = labels! { "fighter" };
+    Hash40::with_label_map(&labels, || {
+        assert_eq!(hash40("fighter").to_label_or(|_| String::new()), "fighter");
+        assert_eq!(hash40("stage").to_label_or(|_| String::new()), "");
+        assert_eq!(
+            Hash40(0x1234).to_label_or(|hash| format!("<unknown {}>", hash.to_hex_string())),
+            "<unknown 0x0000001234>"
+        );
+        assert_eq!(Hash40(0x1234).to_label(), "0x0000001234");
+    });
+}
+
+#[test]
+fn test_from_conversions() {
+    let hash = hash40("fighter");
+    let parts: Hash40 = (hash.crc(), hash.str_len()).into();
+    assert_eq!(parts, hash);
+    assert_eq!(Hash40::from((0x7a08c3fc, 7)), hash);
+
+    assert_eq!(u64::from(hash), 0x077a08c3fc);
+    assert_eq!(u64::from(Hash40::INVALID), Hash40::INVALID.as_u64());
+    assert!(Hash40::try_from(u64::from(Hash40::INVALID)).is_err());
+
+    assert_eq!(u32::try_from(Hash40(0x7a08c3fc)), Ok(0x7a08c3fc));
+    assert!(u32::try_from(hash).is_err());
+    assert!(u32::try_from(Hash40::INVALID).is_err());
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn test_serde_binary_round_trip() {
+    let labels = labels! { "fighter" };
+    Hash40::with_label_map(&labels, || {
+        for hash in [hash40("fighter"), hash40("stage"), Hash40(0x1234)] {
+            let bytes = bincode::serialize(&hash).unwrap();
+            assert_eq!(bincode::deserialize::<Hash40>(&bytes).unwrap(), hash);
+        }
+    });
+}
+
+#[test]
+fn test_invalid_hex_round_trip() {
+    let hex = Hash40::INVALID.to_hex_string();
+    assert_eq!(hex, "0xffffffffffff");
+    assert_eq!(Hash40::from_hex_str(&hex), Ok(Hash40::INVALID));
+    assert_eq!(format!("{:#}", Hash40::INVALID), hex);
+    assert_eq!(format!("{:#x}", Hash40::INVALID), "0xffffffffff");
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn test_invalid_serde_round_trip() {
+    Hash40::with_label_map(&labels! {}, || {
+        assert_eq!(Hash40::INVALID.to_label(), "0xffffffffffff");
+        assert_eq!(Hash40::INVALID.to_label_interned(), "0xffffffffffff");
+        assert_eq!(Hash40(0x1234).to_label_interned(), "0x0000001234");
+        let json = serde_json::to_string(&Hash40::INVALID).unwrap();
+        assert_eq!(json, r#""0xffffffffffff""#);
+        assert_eq!(
+            serde_json::from_str::<Hash40>(&json).unwrap(),
+            Hash40::INVALID
+        );
+    });
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn test_deserialize_negative() {
+    use serde::de::value::{Error, I64Deserializer};
+
+    assert!(serde_json::from_str::<Hash40>("-1").is_err());
+    assert!(Hash40::deserialize(I64Deserializer::<Error>::new(-1)).is_err());
+    assert_eq!(
+        Hash40::deserialize(I64Deserializer::<Error>::new(0x1234)),
+        Ok(Hash40(0x1234))
+    );
+}
+
+#[test]
+#[cfg(all(feature = "std", unix))]
+fn test_from_path_non_utf8() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let a = Path::new(OsStr::from_bytes(b"fighter/\xff"));
+    let b = Path::new(OsStr::from_bytes(b"fighter/\xfe"));
+    assert_ne!(Hash40::from_path(a), Hash40::from_path(b));
+    assert_eq!(Hash40::from_path(a), Hash40::hash_bytes(b"fighter/\xff"));
+}
@@ -0,0 +1,107 @@
+//! A string stored alongside its hash, for strings which are hashed or concatenated repeatedly
+
+use crate::Hash40;
+use alloc::string::String;
+use core::ops::Deref;
+
+/// A string and its Hash40, computed once when the string is created. The string can't be
+/// mutated, so the two always match
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PrehashedStr {
+    hash: Hash40,
+    text: String,
+}
+
+impl PrehashedStr {
+    /// Hashes the string with [`Hash40::new`] and stores it alongside the hash
+    pub fn new<S: Into<String>>(text: S) -> Self {
+        let text = text.into();
+        Self {
+            hash: Hash40::new(&text),
+            text,
+        }
+    }
+
+    /// Returns the hash of the string
+    pub const fn hash(&self) -> Hash40 {
+        self.hash
+    }
+
+    /// Returns the string
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the string, discarding the hash
+    pub fn into_string(self) -> String {
+        self.text
+    }
+
+    /// Concatenates two strings, combining their hashes without rehashing. See
+    /// [`Hash40::concat`] for when the combined hash is exact
+    pub fn concat(&self, other: &PrehashedStr) -> Self {
+        let mut text = String::with_capacity(self.text.len() + other.text.len());
+        text.push_str(&self.text);
+        text.push_str(&other.text);
+        Self {
+            hash: self.hash.concat(other.hash),
+            text,
+        }
+    }
+}
+
+impl Deref for PrehashedStr {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.text
+    }
+}
+
+impl AsRef<str> for PrehashedStr {
+    fn as_ref(&self) -> &str {
+        &self.text
+    }
+}
+
+impl From<String> for PrehashedStr {
+    fn from(text: String) -> Self {
+        Self::new(text)
+    }
+}
+
+impl From<&str> for PrehashedStr {
+    fn from(text: &str) -> Self {
+        Self::new(text)
+    }
+}
+
+impl From<PrehashedStr> for Hash40 {
+    fn from(prehashed: PrehashedStr) -> Self {
+        prehashed.hash
+    }
+}
+
+impl From<&PrehashedStr> for Hash40 {
+    fn from(prehashed: &PrehashedStr) -> Self {
+        prehashed.hash
+    }
+}
+
+#[test]
+fn test_prehashed() {
+    use crate::hash40;
+
+    let prefix = PrehashedStr::new("fighter/");
+    let name = PrehashedStr::from("mario");
+    assert_eq!(prefix.hash(), hash40("fighter/"));
+    assert_eq!(Hash40::from(&name), hash40("mario"));
+
+    let path = prefix.concat(&name);
+    assert_eq!(path.as_str(), "fighter/mario");
+    assert_eq!(path.hash(), hash40("fighter/mario"));
+    assert_eq!(
+        hash40("stage/").concat_prehashed(&name),
+        hash40("stage/mario")
+    );
+}
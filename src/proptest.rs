@@ -0,0 +1,36 @@
+//! Strategies for property testing code which uses [`Hash40`], with the `proptest` crate
+
+use crate::Hash40;
+use ::proptest::prelude::*;
+use alloc::string::String;
+
+/// Generates any hash within the 40-bit range
+pub fn any_hash40() -> impl Strategy<Value = Hash40> {
+    (0..=0xff_ffff_ffffu64).prop_map(Hash40)
+}
+
+/// Generates short lowercase ASCII labels, paired with their hashes
+pub fn any_label() -> impl Strategy<Value = (String, Hash40)> {
+    "[a-z0-9_/]{0,32}".prop_map(|label| {
+        let hash = Hash40::new(&label);
+        (label, hash)
+    })
+}
+
+proptest! {
+    #[test]
+    fn test_from_parts_round_trip(hash in any_hash40()) {
+        prop_assert_eq!(Hash40::from_parts(hash.crc(), hash.str_len()), hash);
+    }
+
+    #[test]
+    fn test_concat_matches_single_pass((a, a_hash) in any_label(), (b, b_hash) in any_label()) {
+        prop_assert_eq!(a_hash.concat(b_hash), Hash40::new(&alloc::format!("{}{}", a, b)));
+    }
+
+    #[test]
+    fn test_label_length((label, hash) in any_label()) {
+        prop_assert_eq!(hash.str_len() as usize, label.len());
+        prop_assert!(hash.verify(&label));
+    }
+}
@@ -0,0 +1,48 @@
+//! Serde helpers which always represent a [`Hash40`] in its hexadecimal form, ignoring the
+//! static label map. Use with `#[serde(with = "hash40::serde_hex")]`
+
+use crate::Hash40;
+use serde::{de, Deserializer, Serializer};
+
+/// Serializes the hash as a hexadecimal string, formatted as `0x0123456789`
+pub fn serialize<S: Serializer>(hash: &Hash40, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&hash.to_hex_string())
+}
+
+/// Deserializes the hash from a hexadecimal string, beginning with "0x"
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Hash40, D::Error> {
+    deserializer.deserialize_str(HexVisitor)
+}
+
+struct HexVisitor;
+
+impl<'de> de::Visitor<'de> for HexVisitor {
+    type Value = Hash40;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("A hex-formatted integer hash value")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        Hash40::from_hex_str(value).map_err(de::Error::custom)
+    }
+}
+
+#[test]
+fn test_serde_hex() {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Config {
+        #[serde(with = "crate::serde_hex")]
+        hash: Hash40,
+    }
+
+    let config = Config {
+        hash: crate::hash40("fighter"),
+    };
+    let json = serde_json::to_string(&config).unwrap();
+    assert_eq!(json, r#"{"hash":"0x077a08c3fc"}"#);
+    assert_eq!(serde_json::from_str::<Config>(&json).unwrap(), config);
+    assert!(serde_json::from_str::<Config>(r#"{"hash":"fighter"}"#).is_err());
+}
@@ -0,0 +1,39 @@
+//! Serde helpers which represent a [`Hash40`] as its raw integer value, for compact binary
+//! formats. Use with `#[serde(with = "hash40::serde_u64")]`
+
+use crate::Hash40;
+use serde::{Deserialize, Deserializer, Serializer};
+
+/// Serializes the hash as a u64
+pub fn serialize<S: Serializer>(hash: &Hash40, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(hash.0)
+}
+
+/// Deserializes the hash from a u64, keeping only the lower 40 bits
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Hash40, D::Error> {
+    u64::deserialize(deserializer).map(|value| Hash40(value & 0xff_ffff_ffff))
+}
+
+#[test]
+fn test_serde_u64() {
+    use serde::Serialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Config {
+        #[serde(with = "crate::serde_u64")]
+        hash: Hash40,
+    }
+
+    let config = Config {
+        hash: crate::hash40("fighter"),
+    };
+    let json = serde_json::to_string(&config).unwrap();
+    assert_eq!(json, r#"{"hash":32112165884}"#);
+    assert_eq!(serde_json::from_str::<Config>(&json).unwrap(), config);
+    assert_eq!(
+        serde_json::from_str::<Config>(r#"{"hash":18446744073709551615}"#).unwrap(),
+        Config {
+            hash: Hash40(0xff_ffff_ffff)
+        }
+    );
+}